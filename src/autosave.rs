@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use tokio::{select, task::JoinHandle, time::sleep};
+
+use crate::bot::Data;
+
+/// nach einer Änderung wird kurz gewartet, damit mehrere Änderungen hintereinander nur einmal gespeichert werden
+const CHANGE_DELAY: Duration = Duration::from_secs(5);
+
+/// speichert die todos regelmäßig und kurz nach jeder Änderung, damit bei einem Absturz nichts verloren geht
+pub fn spawn_autosave_worker(data: Data, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let changes = data.lock().await.changes();
+        loop {
+            select! {
+                _ = sleep(interval) => (),
+                _ = changes.notified() => sleep(CHANGE_DELAY).await,
+            }
+
+            let mut data = data.lock().await;
+            if !data.is_dirty() {
+                continue;
+            }
+            match data.save() {
+                Ok(_) => log::trace!("autosaved todos"),
+                Err(e) => log::error!("Error when autosaving todos: {e}"),
+            }
+        }
+    })
+}
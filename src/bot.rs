@@ -1,8 +1,16 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use handle_commands::{
-    handle_add_todo, handle_check_command, handle_list_todos, split_command_message,
+    handle_add_todo, handle_check_command, handle_done_command, handle_edit_command,
+    handle_flush_command, handle_list_todos, handle_move_command, handle_prio_command,
+    handle_start_command, handle_stats_command, handle_subtask_command, handle_tags_command,
+    handle_top_command, handle_undo_command, split_command_message, PendingFlushes,
 };
+use handle_mod_commands::{handle_clear_command, handle_mod_add_command, handle_remove_command};
+use handle_stream_commands::{
+    handle_list_stream_todos, handle_stream_end_command, handle_stream_todo_command,
+};
+use handle_timers::{handle_focus_command, handle_pomodoro_command};
 use tokio::{
     sync::{
         mpsc::{Sender, UnboundedReceiver},
@@ -13,16 +21,19 @@ use tokio::{
 use twitch_irc::message::{self, ServerMessage};
 
 use crate::{
-    communication::{BotMessage, TodoUpdate},
-    config::{save_data, ModSet},
+    communication::{BotMessage, TodoSubscribers},
+    config::{save_data, BotConfig, ModSet},
     lang::lang,
+    storage::TodoData,
+    timer::Timers,
 };
 
 mod handle_commands;
+mod handle_mod_commands;
+mod handle_stream_commands;
+mod handle_timers;
 
-pub use handle_commands::hash_message;
-
-pub type Data = Arc<Mutex<HashMap<String, Vec<String>>>>;
+pub type Data = Arc<Mutex<TodoData>>;
 
 /*
     hier sind die konstanten abgebildet, die die Kommandos (ohne Ausrufezeichen repräsentieren)
@@ -33,77 +44,209 @@ const CHECK_TODO_COMMAND: &str = "check";
 const TODO_HELP: &str = "todohelp";
 const FLUSH_TODOS: &str = "todoflush";
 const SAVE_TODO: &str = "savetodos";
+const DONE_TODOS: &str = "done";
+const UNDO_TODO: &str = "todoundo";
+const EDIT_TODO: &str = "todoedit";
+const PRIO_TODO: &str = "todoprio";
+const MOVE_TODO: &str = "todomove";
+const SUBTASK_TODO: &str = "todosub";
+const TAGS_TODO: &str = "todotags";
+const START_TODO: &str = "todostart";
+const FOCUS: &str = "focus";
+const POMODORO: &str = "pomodoro";
+const REMOVE_USER_TODO: &str = "todoremove";
+const CLEAR_USER_TODOS: &str = "todoclear";
+const ADD_USER_TODO: &str = "todoadd";
+const STREAM_TODO: &str = "streamtodo";
+const LIST_STREAM_TODOS: &str = "streamtodos";
+const STREAM_END: &str = "streamend";
+const STATS_TODO: &str = "todostats";
+const TOP_TODO: &str = "todotop";
 
 pub fn create_bot_worker(
     mut incoming_messages: UnboundedReceiver<ServerMessage>,
     client: Sender<BotMessage>,
     data: Data,
     mods: Arc<Mutex<ModSet>>,
-    todo_subscribers: Arc<Mutex<Vec<Sender<TodoUpdate>>>>,
+    todo_subscribers: TodoSubscribers,
+    timers: Timers,
+    config: BotConfig,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         // hier werden die Daten gespeichert
+        let mut pending_flushes = PendingFlushes::new();
 
         while let Some(message) = incoming_messages.recv().await {
             match message {
-                message::ServerMessage::Privmsg(msg) => {
-                    if (&msg).message_text.starts_with("!") {
-                        let (cmd, text) = split_command_message(msg.message_text.clone());
+                message::ServerMessage::Privmsg(msg) if msg.message_text.starts_with('!') => {
+                    let (cmd, text) = split_command_message(msg.message_text.clone());
 
-                        // hier werden die einzelnen Kommandos gecheckt
-                        // wenn eines der Kommandos (bspw. ADD_TODO_COMMAND) am Anfang steht, werden diese abgehandelt
+                    // hier werden die einzelnen Kommandos gecheckt
+                    // wenn eines der Kommandos (bspw. ADD_TODO_COMMAND) am Anfang steht, werden diese abgehandelt
 
-                        if let Some(response) = match cmd.as_str() {
-                            ADD_TODO_COMMAND => {
-                                log::info!("adding command: {:?}", &text);
-                                handle_add_todo(text, data.clone(), &msg, &todo_subscribers).await
-                            }
-                            LIST_TODO_COMMAND => handle_list_todos(text, data.clone(), &msg).await,
-                            CHECK_TODO_COMMAND => {
-                                log::info!("checked command: {:?}", &text);
-                                handle_check_command(text, data.clone(), &msg, &todo_subscribers).await
-                            }
-                            TODO_HELP => Some(lang::HELP_REPLY.to_owned()),
-                            FLUSH_TODOS => {
-                                if mods.lock().await.set.contains(&msg.sender.login) {
-                                    log::warn!(
-                                        "flushed data: {}\n",
-                                        data.lock()
-                                            .await
-                                            .drain()
-                                            .map(|e| format!("[{}, {:?}]", e.0, e.1))
-                                            .collect::<String>()
-                                    );
-                                }
-                                Some(lang::FLUSHED_TODOS.to_owned())
-                            }
-                            SAVE_TODO => {
-                                if mods.lock().await.set.contains(&msg.sender.login) {
-                                    match save_data(&data).await {
-                                        Ok(_) => {
-                                            log::warn!("saved data");
-                                            Some(lang::SAVED_DATA.to_owned())
-                                        }
-                                        Err(e) => {
-                                            log::error!("Error when saving todos: {e}");
-                                            Some(lang::ERROR_WHEN_SAVING_DATA.to_owned())
-                                        }
+                    if let Some(response) = match cmd.as_str() {
+                        ADD_TODO_COMMAND => {
+                            log::info!("adding command: {:?}", &text);
+                            handle_add_todo(
+                                text,
+                                data.clone(),
+                                &msg,
+                                config.limits_for(&msg.channel_login),
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        LIST_TODO_COMMAND => handle_list_todos(text, data.clone(), &msg).await,
+                        CHECK_TODO_COMMAND => {
+                            log::info!("checked command: {:?}", &text);
+                            handle_check_command(text, data.clone(), &msg, &todo_subscribers).await
+                        }
+                        START_TODO => {
+                            log::info!("start command: {:?}", &text);
+                            handle_start_command(text, data.clone(), &msg, &todo_subscribers).await
+                        }
+                        EDIT_TODO => {
+                            log::info!("edit command: {:?}", &text);
+                            handle_edit_command(
+                                text,
+                                data.clone(),
+                                &msg,
+                                config.limits_for(&msg.channel_login),
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        SUBTASK_TODO => {
+                            log::info!("subtask command: {:?}", &text);
+                            handle_subtask_command(
+                                text,
+                                data.clone(),
+                                &msg,
+                                config.limits_for(&msg.channel_login),
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        PRIO_TODO => {
+                            handle_prio_command(text, data.clone(), &msg, &todo_subscribers).await
+                        }
+                        MOVE_TODO => {
+                            handle_move_command(text, data.clone(), &msg, &todo_subscribers).await
+                        }
+                        TAGS_TODO => handle_tags_command(data.clone(), &msg).await,
+                        FOCUS => handle_focus_command(text, data.clone(), &msg, &timers).await,
+                        POMODORO => {
+                            let is_mod = mods.lock().await.set.contains(&msg.sender.login);
+                            handle_pomodoro_command(text, &msg, is_mod, &timers).await
+                        }
+                        REMOVE_USER_TODO => {
+                            let is_mod = mods.lock().await.set.contains(&msg.sender.login);
+                            handle_remove_command(
+                                text,
+                                data.clone(),
+                                &msg,
+                                is_mod,
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        CLEAR_USER_TODOS => {
+                            let is_mod = mods.lock().await.set.contains(&msg.sender.login);
+                            handle_clear_command(
+                                text,
+                                data.clone(),
+                                &msg,
+                                is_mod,
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        ADD_USER_TODO => {
+                            let is_mod = mods.lock().await.set.contains(&msg.sender.login);
+                            handle_mod_add_command(
+                                text,
+                                data.clone(),
+                                &msg,
+                                is_mod,
+                                config.limits_for(&msg.channel_login),
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        LIST_STREAM_TODOS => handle_list_stream_todos(data.clone(), &msg).await,
+                        STREAM_TODO => {
+                            // die Liste gehört dem Streamer, seine mods dürfen ihm helfen
+                            let can_manage = msg.sender.login == msg.channel_login
+                                || mods.lock().await.set.contains(&msg.sender.login);
+                            handle_stream_todo_command(
+                                text,
+                                data.clone(),
+                                &msg,
+                                can_manage,
+                                config.limits_for(&msg.channel_login),
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        STREAM_END => {
+                            let can_manage = msg.sender.login == msg.channel_login
+                                || mods.lock().await.set.contains(&msg.sender.login);
+                            handle_stream_end_command(
+                                data.clone(),
+                                &msg,
+                                can_manage,
+                                config.retention_for(&msg.channel_login),
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        STATS_TODO => handle_stats_command(text, data.clone(), &msg).await,
+                        TOP_TODO => handle_top_command(data.clone(), &msg).await,
+                        DONE_TODOS => handle_done_command(text, data.clone(), &msg).await,
+                        UNDO_TODO => {
+                            let is_mod = mods.lock().await.set.contains(&msg.sender.login);
+                            handle_undo_command(text, data.clone(), &msg, is_mod, &todo_subscribers)
+                                .await
+                        }
+                        TODO_HELP => Some(lang::HELP_REPLY.to_owned()),
+                        FLUSH_TODOS => {
+                            let is_mod = mods.lock().await.set.contains(&msg.sender.login);
+                            handle_flush_command(
+                                text,
+                                data.clone(),
+                                &msg,
+                                is_mod,
+                                &mut pending_flushes,
+                                &todo_subscribers,
+                            )
+                            .await
+                        }
+                        SAVE_TODO => {
+                            if mods.lock().await.set.contains(&msg.sender.login) {
+                                match save_data(&data).await {
+                                    Ok(_) => {
+                                        log::warn!("saved data");
+                                        Some(lang::SAVED_DATA.to_owned())
+                                    }
+                                    Err(e) => {
+                                        log::error!("Error when saving todos: {e}");
+                                        Some(lang::ERROR_WHEN_SAVING_DATA.to_owned())
                                     }
-                                } else {
-                                    Some(lang::NO_PERMISSION.to_owned())
                                 }
+                            } else {
+                                Some(lang::NO_PERMISSION.to_owned())
                             }
-                            _ => None,
-                        } {
-                            client
-                                .send(BotMessage {
-                                    reciever: Some(msg.sender.login),
-                                    message: response,
-                                    channel: msg.channel_login,
-                                })
-                                .await
-                                .unwrap();
                         }
+                        _ => None,
+                    } {
+                        client
+                            .send(BotMessage {
+                                reciever: Some(msg.sender.login),
+                                message: response,
+                                channel: msg.channel_login,
+                            })
+                            .await
+                            .unwrap();
                     }
                 }
                 ServerMessage::Notice(s) => {
@@ -1,13 +1,18 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, Instant},
+};
 
-use tokio::sync::{mpsc::Sender, Mutex};
+use chrono::{DateTime, Local, NaiveTime, TimeDelta, TimeZone, Utc};
 use twitch_irc::message::PrivmsgMessage;
 
-use std::hash::{DefaultHasher, Hasher};
-
 use crate::{
-    communication::TodoUpdate,
+    communication::{send_todo_update, TodoSubscribers, TodoUpdate},
+    config::{LimitExceeded, TodoLimits},
     lang::lang::{self, YOUR_TODOS},
+    leaderboard::LeaderboardEntry,
+    stats::TagUsage,
+    todo::{FlushScope, HistoryQuery, Todo, TodoPriority},
 };
 
 use super::Data;
@@ -17,72 +22,176 @@ pub async fn handle_list_todos(
     data: Data,
     msg: &PrivmsgMessage,
 ) -> Option<String> {
-    let sender = match text {
-        None => &msg.sender.login,
-        Some(s) => {
-            let s = s.replace(" ", "");
-            if s != "".to_owned() {
-                &s.clone()
-            } else {
-                &msg.sender.login
-            }
-        }
+    // !todos #tag zeigt nur die todos mit diesem Tag, alles andere ist der Nutzer
+    let text = text.unwrap_or_default();
+    let (tags, user): (Vec<&str>, Vec<&str>) = text
+        .split_whitespace()
+        .partition(|word| word.starts_with('#'));
+    let sender = match user.concat() {
+        s if !s.is_empty() => s,
+        _ => msg.sender.login.clone(),
     };
-    Some(format_message_reply(data.lock().await.get(sender)))
+    Some(format_message_reply(
+        data.lock().await.user_todos(&msg.channel_login, &sender),
+        tags.first().copied(),
+    ))
 }
 
 pub fn split_command_message(message: String) -> (String, Option<String>) {
-    return if message.contains(" ") {
-        let (cmd, text) = message.split_once(" ").unwrap();
+    if message.contains(' ') {
+        let (cmd, text) = message.split_once(' ').unwrap();
         (cmd[1..].to_owned(), Some(text.to_owned()))
     } else {
         (message[1..].to_owned(), None)
-    };
+    }
 }
 
 pub async fn handle_add_todo(
     text: Option<String>,
     data: Data,
     msg: &PrivmsgMessage,
-    todo_subscribers: &Arc<Mutex<Vec<Sender<TodoUpdate>>>>,
+    limits: &TodoLimits,
+    todo_subscribers: &TodoSubscribers,
 ) -> Option<String> {
     if let Some(text) = text {
+        // !todo 45m ... oder !todo at 18:00 ... setzt eine Zeit, zu der erinnert wird
+        let (due_at, text) = parse_due_time(&text);
         let mut data_locked = data.lock().await;
-        match data_locked.get_mut(&msg.sender.login) {
-            Some(s) => s.push(text.to_owned()),
-            None => {
-                data_locked.insert(msg.sender.login.clone(), vec![text.to_owned()]);
-            }
+        if let Err(exceeded) =
+            limits.check(&data_locked, &msg.channel_login, &msg.sender.login, &text)
+        {
+            log::info!("rejected todo of {}: {:?}", msg.sender.login, exceeded);
+            return Some(format_limit_reply(exceeded));
         }
+        let todo = data_locked.add(&msg.channel_login, &msg.sender, text, due_at);
+        let reorder = data_locked
+            .is_reordered(&todo)
+            .then(|| user_todo_ids(data_locked.user_todos(&msg.channel_login, &msg.sender.login)));
+        drop(data_locked);
 
-        let mut subscriber_lock = todo_subscribers.lock().await;
+        send_todo_update(todo_subscribers, TodoUpdate::AddTodo(todo)).await;
+        if let Some(ids) = reorder {
+            send_todo_update(
+                todo_subscribers,
+                TodoUpdate::ReorderTodos {
+                    channel: msg.channel_login.clone(),
+                    ids,
+                },
+            )
+            .await;
+        }
+        if let Some(due_at) = due_at {
+            return Some(format!(
+                "{} {}",
+                lang::REMINDER_SET,
+                format_due_time(due_at)
+            ));
+        }
+    }
+    None
+}
 
-        let todo_update = TodoUpdate::AddTodo {
-            user: msg.sender.login.clone(),
-            uuid: hash_message(&msg.sender.login, &text),
-            todo_message: text,
-        };
+/// Wörter, mit denen eine Uhrzeit eingeleitet wird (!todo at 18:00 ...)
+const DUE_AT_WORDS: [&str; 2] = ["at", "um"];
 
-        for subscriber in subscriber_lock.clone().into_iter() {
-            let _ = subscriber.send(todo_update.clone()).await;
+/// liest eine Zeit am Anfang des todos: eine Dauer (45m, 2h) oder eine Uhrzeit (at 18:00, lokale Zeit)
+/// ist die Uhrzeit heute schon vorbei, ist morgen gemeint
+pub fn parse_due_time(text: &str) -> (Option<DateTime<Utc>>, String) {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let (due_at, rest) = match words.as_slice() {
+        [word, time, rest @ ..] if DUE_AT_WORDS.contains(&word.to_lowercase().as_str()) => {
+            (parse_clock_time(time), rest)
         }
-        subscriber_lock.retain(|e| !e.is_closed());
-        drop(subscriber_lock);
+        [duration, rest @ ..] => (parse_duration(duration).map(|d| Utc::now() + d), rest),
+        [] => (None, &[][..]),
+    };
+    match due_at {
+        Some(due_at) if !rest.is_empty() => (Some(due_at), rest.join(" ")),
+        _ => (None, text.to_owned()),
     }
-    None
 }
 
-pub fn format_message_reply(todos: Option<&Vec<String>>) -> String {
+fn parse_clock_time(text: &str) -> Option<DateTime<Utc>> {
+    let time = NaiveTime::parse_from_str(text, "%H:%M").ok()?;
+    let now = Local::now();
+    let mut due_at = now.date_naive().and_time(time);
+    if due_at <= now.naive_local() {
+        due_at += TimeDelta::days(1);
+    }
+    Local
+        .from_local_datetime(&due_at)
+        .earliest()
+        .map(|due_at| due_at.with_timezone(&Utc))
+}
+
+/// Uhrzeit in lokaler Zeit, liegt sie mehr als einen Tag in der Zukunft mit Datum
+fn format_due_time(due_at: DateTime<Utc>) -> String {
+    let due_at = due_at.with_timezone(&Local);
+    if due_at - Local::now() < TimeDelta::hours(24) {
+        due_at.format("%H:%M").to_string()
+    } else {
+        due_at.format("%d.%m. %H:%M").to_string()
+    }
+}
+
+pub fn format_limit_reply(exceeded: LimitExceeded) -> String {
+    let (reply, max) = match exceeded {
+        LimitExceeded::UserTodos(max) => (lang::TOO_MANY_USER_TODOS, max),
+        LimitExceeded::TextLength(max) => (lang::TODO_TEXT_TOO_LONG, max),
+        LimitExceeded::ChannelTodos(max) => (lang::TOO_MANY_CHANNEL_TODOS, max),
+    };
+    format!("{} {} {}", reply.0, max, reply.1)
+}
+
+/// die todos mit ihrer Nummer, mit tag nur die todos mit diesem Tag (die Nummern bleiben gleich)
+pub fn format_message_reply(todos: Option<&Vec<Todo>>, tag: Option<&str>) -> String {
     match todos {
         None => lang::NO_TODOS_ADDEDD_YET.to_owned(),
         Some(todos) => {
             let mut todos_str = String::new();
-            let mut index = 1;
-            for todo in todos {
-                todos_str.push_str(format!("({}) ", index).as_str());
-                todos_str.push_str(todo.as_str());
+            let todos = todos
+                .iter()
+                .enumerate()
+                .filter(|(_, todo)| tag.is_none_or(|tag| todo.has_tag(tag)));
+            for (index, todo) in todos {
+                todos_str.push_str(format!("({}) ", index + 1).as_str());
+                if todo.is_overdue() {
+                    todos_str.push_str(lang::OVERDUE_MARK);
+                } else if let Some(due_at) = todo.due_at {
+                    todos_str.push_str(
+                        format!("[{} {}] ", lang::DUE_MARK, format_due_time(due_at)).as_str(),
+                    );
+                }
+                if todo.is_in_progress() {
+                    todos_str.push_str(lang::IN_PROGRESS_MARK);
+                }
+                match todo.priority {
+                    TodoPriority::High => todos_str.push_str(lang::HIGH_PRIORITY_MARK),
+                    TodoPriority::Low => todos_str.push_str(lang::LOW_PRIORITY_MARK),
+                    TodoPriority::Normal => {}
+                }
+                todos_str.push_str(todo.text_with_tags().as_str());
                 todos_str.push(' ');
-                index += 1;
+                if !todo.subtasks.is_empty() {
+                    let (done, total) = todo.progress();
+                    todos_str.push_str(format!("[{done}/{total}:").as_str());
+                    for (subtask_index, subtask) in todo.subtasks.iter().enumerate() {
+                        let mark = if subtask.done { "✓ " } else { "" };
+                        todos_str.push_str(
+                            format!(
+                                " ({}.{}) {mark}{}",
+                                index + 1,
+                                subtask_index + 1,
+                                subtask.text
+                            )
+                            .as_str(),
+                        );
+                    }
+                    todos_str.push_str("] ");
+                }
+            }
+            if todos_str.is_empty() && tag.is_some() {
+                return lang::NO_TODOS_WITH_TAG.to_owned();
             }
             format!("{YOUR_TODOS} {todos_str}")
         }
@@ -93,48 +202,882 @@ pub async fn handle_check_command(
     text: Option<String>,
     data: Data,
     msg: &PrivmsgMessage,
-    todo_subscribers: &Arc<Mutex<Vec<Sender<TodoUpdate>>>>,
+    todo_subscribers: &TodoSubscribers,
 ) -> Option<String> {
-    let index: usize = match text {
-        Some(s) => match s.parse() {
-            Ok(r) => {
-                if r > 0 {
-                    r - 1
-                } else {
-                    r
-                }
-            }
-            Err(_) => 0,
+    // !check 2.1 hakt den ersten Unterpunkt des zweiten todos ab
+    if let Some((index, subtask)) = text.as_deref().and_then(parse_subtask_number) {
+        return handle_check_subtask(index, subtask, data, msg, todo_subscribers).await;
+    }
+    let mut data_locked = data.lock().await;
+    let todos = match data_locked.user_todos(&msg.channel_login, &msg.sender.login) {
+        Some(todos) if !todos.is_empty() => todos,
+        _ => return Some(lang::NO_TODOS_ADDEDD_YET.to_owned()),
+    };
+    let indices = match parse_check_selection(text.as_deref(), todos) {
+        Ok(indices) => indices,
+        Err(reply) => return Some(reply),
+    };
+    // von hinten abhaken, damit die übrigen Nummern gleich bleiben
+    let mut checked_todos: Vec<Todo> = indices
+        .into_iter()
+        .rev()
+        .filter_map(|index| data_locked.check(&msg.channel_login, &msg.sender.login, index))
+        .collect();
+    drop(data_locked);
+    checked_todos.reverse();
+    log::info!("completed todos: {:?}", checked_todos);
+
+    send_todo_update(
+        todo_subscribers,
+        TodoUpdate::CheckTodo {
+            channel: msg.channel_login.clone(),
+            ids: checked_todos.iter().map(|todo| todo.id).collect(),
         },
-        None => 0,
+    )
+    .await;
+
+    let checked_str = checked_todos
+        .iter()
+        .map(|todo| format!("{}{}", todo.text, format_time_spent(todo)))
+        .collect::<Vec<String>>()
+        .join(", ");
+    Some(format!(
+        "{} {} {} {}",
+        msg.sender.login,
+        lang::FINISHED_TODO.0,
+        checked_str,
+        lang::FINISHED_TODO.1
+    ))
+}
+
+/// welche todos ein !check meint, als Indizes aufsteigend
+/// ohne Text das erste, sonst Nummern (1 3 5 oder 1,3), Bereiche (2-4), all oder "Text"
+/// bei ungültiger Eingabe kommt die Antwort für den Chat zurück, damit nie aus Versehen etwas abgehakt wird
+fn parse_check_selection(text: Option<&str>, todos: &[Todo]) -> Result<Vec<usize>, String> {
+    let text = match text.map(str::trim) {
+        None | Some("") => return Ok(vec![0]),
+        Some(text) => text,
     };
-    if let Some(user_todos) = data.lock().await.get_mut(&msg.sender.login) {
-        if index >= user_todos.len() {
-            return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned());
-        }
-        let checked_todo = user_todos.remove(index);
+    if text.eq_ignore_ascii_case("all") {
+        return Ok((0..todos.len()).collect());
+    }
+    if let Some(fragment) = text.strip_prefix('"') {
+        return find_todo_by_text(fragment.trim_end_matches('"'), todos).map(|index| vec![index]);
+    }
 
-        let mut subscriber_lock = todo_subscribers.lock().await;
-        let todo_update = TodoUpdate::CheckTodo(hash_message(&msg.channel_login, &checked_todo));
+    let mut indices = BTreeSet::new();
+    for part in text.split([' ', ',']).filter(|part| !part.is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (first.parse::<usize>(), last.parse::<usize>()),
+            None => (part.parse::<usize>(), part.parse::<usize>()),
+        };
+        let (first, last) = match (first, last) {
+            (Ok(first), Ok(last)) if first > 0 && first <= last => (first, last),
+            _ => return Err(lang::CHECK_USAGE.to_owned()),
+        };
+        if last > todos.len() {
+            return Err(lang::TASK_INDEX_DOESNT_EXIST.to_owned());
+        }
+        indices.extend(first - 1..last);
+    }
+    Ok(indices.into_iter().collect())
+}
 
-        for subscriber in subscriber_lock.clone().into_iter() {
-            let _ = subscriber.send(todo_update.clone()).await;
+/// sucht das todo, dessen Text zum Fragment passt, ohne auf Groß- und Kleinschreibung zu achten
+/// erst wird nach dem Fragment im Text gesucht, findet das nichts, reicht es, wenn die Buchstaben
+/// in der Reihenfolge vorkommen ("mth hw" findet "math homework")
+fn find_todo_by_text(fragment: &str, todos: &[Todo]) -> Result<usize, String> {
+    let fragment = fragment.trim().to_lowercase();
+    if fragment.is_empty() {
+        return Err(lang::CHECK_USAGE.to_owned());
+    }
+    let texts: Vec<String> = todos.iter().map(|todo| todo.text.to_lowercase()).collect();
+    let mut matches: Vec<usize> = (0..texts.len())
+        .filter(|index| texts[*index].contains(&fragment))
+        .collect();
+    if matches.is_empty() {
+        matches = (0..texts.len())
+            .filter(|index| is_subsequence(&fragment, &texts[*index]))
+            .collect();
+    }
+    match matches.as_slice() {
+        [] => Err(lang::NO_MATCHING_TODO.to_owned()),
+        [index] => Ok(*index),
+        _ => {
+            let numbers = matches
+                .iter()
+                .map(|index| (index + 1).to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            Err(format!("{} {numbers}", lang::SEVERAL_MATCHING_TODOS))
         }
-        subscriber_lock.retain(|e| !e.is_closed());
+    }
+}
+
+/// kommen alle Zeichen von fragment (ohne Leerzeichen) in der Reihenfolge in text vor?
+fn is_subsequence(fragment: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    fragment
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|c| chars.any(|t| t == c))
+}
+
+/// liest eine Nummer wie 2.1 als (todo, unterpunkt), beide ab 0
+fn parse_subtask_number(text: &str) -> Option<(usize, usize)> {
+    let (index, subtask) = text.trim().split_once('.')?;
+    let index = index.parse::<usize>().ok()?.checked_sub(1)?;
+    let subtask = subtask.parse::<usize>().ok()?.checked_sub(1)?;
+    Some((index, subtask))
+}
 
+async fn handle_check_subtask(
+    index: usize,
+    subtask: usize,
+    data: Data,
+    msg: &PrivmsgMessage,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    let checked =
+        data.lock()
+            .await
+            .check_subtask(&msg.channel_login, &msg.sender.login, index, subtask);
+    let (todo, completed) = match checked {
+        Some(checked) => checked,
+        None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+    log::info!("completed subtask {}: {:?}", subtask + 1, todo);
+
+    let (done, total) = todo.progress();
+    let reply = format!(
+        "{} {} {} {} ({done}/{total})",
+        msg.sender.login,
+        lang::FINISHED_TODO.0,
+        todo.subtasks[subtask].text,
+        lang::FINISHED_TODO.1
+    );
+    if !completed {
+        send_todo_update(todo_subscribers, TodoUpdate::EditTodo(todo)).await;
+        return Some(reply);
+    }
+
+    // alle Unterpunkte erledigt, damit ist auch das todo geschafft
+    send_todo_update(
+        todo_subscribers,
+        TodoUpdate::CheckTodo {
+            channel: msg.channel_login.clone(),
+            ids: vec![todo.id],
+        },
+    )
+    .await;
+    Some(format!(
+        "{reply} {} {} {} {}{}",
+        msg.sender.login,
+        lang::FINISHED_TODO.0,
+        todo.text,
+        lang::FINISHED_TODO.1,
+        format_time_spent(&todo)
+    ))
+}
+
+/// " (Zeit: 1 h 5 min)" für todos, an denen mit !todostart gearbeitet wurde, sonst leer
+fn format_time_spent(todo: &Todo) -> String {
+    if todo.time_spent_secs == 0 {
+        return String::new();
+    }
+    format!(
+        " ({} {})",
+        lang::TIME_SPENT,
+        format_duration(todo.time_spent())
+    )
+}
+
+/// eine Dauer für den Chat, z.B. 45 min, 1 h 5 min oder 3 d 2 h
+fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes();
+    match minutes {
+        ..60 => format!("{minutes} min"),
+        60..1440 => format!("{} h {} min", minutes / 60, minutes % 60),
+        _ => format!("{} d {} h", minutes / 1440, minutes % 1440 / 60),
+    }
+}
+
+pub async fn handle_start_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    let index = match text.as_deref().map(str::trim).map(str::parse::<usize>) {
+        Some(Ok(index)) if index > 0 => index - 1,
+        _ => return Some(lang::START_USAGE.to_owned()),
+    };
+    let started = data
+        .lock()
+        .await
+        .start(&msg.channel_login, &msg.sender.login, index);
+    let (todo, paused) = match started {
+        Some(started) => started,
+        None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+    log::info!("started todo: {:?}", todo);
+
+    let reply = format!("{} {} {}", msg.sender.login, lang::STARTED_TODO, todo.text);
+    if let Some(paused) = paused {
+        send_todo_update(todo_subscribers, TodoUpdate::EditTodo(paused)).await;
+    }
+    send_todo_update(todo_subscribers, TodoUpdate::StartTodo(todo)).await;
+    Some(reply)
+}
+
+pub async fn handle_subtask_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    limits: &TodoLimits,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    let (index, subtask_text) = match parse_index_argument(text.as_deref()) {
+        Some((index, subtask_text)) if !subtask_text.is_empty() => (index, subtask_text),
+        _ => return Some(lang::SUBTASK_USAGE.to_owned()),
+    };
+    if let Err(exceeded) = limits.check_text(subtask_text) {
+        return Some(format_limit_reply(exceeded));
+    }
+    let todo = match index {
+        Some(index) => data.lock().await.add_subtask(
+            &msg.channel_login,
+            &msg.sender.login,
+            index,
+            subtask_text.to_owned(),
+        ),
+        None => None,
+    };
+    let todo = match todo {
+        Some(todo) => todo,
+        None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+    log::info!("added subtask: {:?}", todo);
+
+    let (done, total) = todo.progress();
+    let reply = format!(
+        "{} {} {} ({done}/{total})",
+        msg.sender.login,
+        lang::ADDED_SUBTASK,
+        subtask_text
+    );
+    send_todo_update(todo_subscribers, TodoUpdate::EditTodo(todo)).await;
+    Some(reply)
+}
+
+/// so lange wartet ein !todoflush auf !todoflush confirm
+const FLUSH_CONFIRM_WINDOW: Duration = Duration::from_secs(60);
+const FLUSH_CONFIRM: &str = "confirm";
+const FLUSH_ALL_CHANNELS: &str = "all";
+
+/// angefragte flushes, die noch bestätigt werden müssen, nach Channel und mod
+pub type PendingFlushes = HashMap<(String, String), (FlushScope, Instant)>;
+
+pub async fn handle_flush_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    is_mod: bool,
+    pending_flushes: &mut PendingFlushes,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    if !is_mod {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+    let key = (msg.channel_login.clone(), msg.sender.login.clone());
+    let args: Vec<String> = text
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+
+    if !args.iter().any(|arg| arg == FLUSH_CONFIRM) {
+        // erster Schritt: nur zählen und auf die Bestätigung warten
+        let scope = match parse_flush_scope(&args, &msg.channel_login) {
+            Some(scope) => scope,
+            None => return Some(lang::FLUSH_USAGE.to_owned()),
+        };
+        let count = data.lock().await.count(&scope);
+        if count == 0 {
+            return Some(lang::NOTHING_TO_FLUSH.to_owned());
+        }
+        pending_flushes.insert(key, (scope, Instant::now()));
         return Some(format!(
-            "{} {} {checked_todo} {}",
-            msg.sender.login,
-            lang::FINISHED_TODO.0,
-            lang::FINISHED_TODO.1
+            "{} {} {}",
+            lang::CONFIRM_FLUSH.0,
+            count,
+            lang::CONFIRM_FLUSH.1
         ));
     }
-    None
+
+    let scope = match pending_flushes.remove(&key) {
+        Some((scope, time)) if time.elapsed() <= FLUSH_CONFIRM_WINDOW => scope,
+        _ => return Some(lang::NO_FLUSH_TO_CONFIRM.to_owned()),
+    };
+    let flushed = data.lock().await.flush(&scope, &msg.channel_login);
+    log::warn!(
+        "flushed data: {}\n",
+        flushed
+            .iter()
+            .map(|e| format!("[{}, {}, {:?}]", e.channel, e.user, e))
+            .collect::<String>()
+    );
+
+    // ein Event pro Channel, damit jedes Overlay nur seine todos bekommt
+    let mut flushed_ids: HashMap<String, Vec<u64>> = HashMap::new();
+    for todo in flushed.iter() {
+        flushed_ids
+            .entry(todo.channel.clone())
+            .or_default()
+            .push(todo.id);
+    }
+    for (channel, ids) in flushed_ids {
+        send_todo_update(todo_subscribers, TodoUpdate::FlushTodos { channel, ids }).await;
+    }
+    Some(format!("{} ({})", lang::FLUSHED_TODOS, flushed.len()))
+}
+
+/// liest die Argumente von !todoflush: @nutzer, ein Alter (z.B. 2h) und all für alle Channels
+fn parse_flush_scope(args: &[String], channel: &str) -> Option<FlushScope> {
+    let mut scope = FlushScope {
+        channel: Some(channel.to_owned()),
+        ..Default::default()
+    };
+    for arg in args {
+        if arg == FLUSH_ALL_CHANNELS {
+            scope.channel = None;
+        } else if let Some(user) = arg.strip_prefix('@') {
+            if user.is_empty() {
+                return None;
+            }
+            scope.user = Some(user.to_owned());
+        } else {
+            scope.older_than = Some(Utc::now() - parse_duration(arg)?);
+        }
+    }
+    Some(scope)
 }
 
-pub fn hash_message(name: &String, text: &String) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    hasher.write(text.as_bytes());
-    hasher.write(name.as_bytes());
-    hasher.finish()
+/// liest eine Dauer wie 45m, 2h, 7d oder 2w, 0 ist keine Dauer (eine Erinnerung käme sofort)
+pub fn parse_duration(text: &str) -> Option<TimeDelta> {
+    let unit = text.chars().last()?;
+    let number: u32 = text[..text.len() - unit.len_utf8()].parse().ok()?;
+    if number == 0 {
+        return None;
+    }
+    let number = i64::from(number);
+    match unit {
+        'm' => TimeDelta::try_minutes(number),
+        'h' => TimeDelta::try_hours(number),
+        'd' => TimeDelta::try_days(number),
+        'w' => TimeDelta::try_weeks(number),
+        _ => None,
+    }
+}
+
+/// Argument für !todoundo, mit dem mods die letzte Aktion eines mods (!todoflush) rückgängig machen
+const UNDO_MOD_ACTION: &str = "mod";
+
+pub async fn handle_undo_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    is_mod: bool,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    let undo_mod_action = text.is_some_and(|s| s.trim().eq_ignore_ascii_case(UNDO_MOD_ACTION));
+    if undo_mod_action && !is_mod {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+
+    let mut data_locked = data.lock().await;
+    let restored = if undo_mod_action {
+        data_locked.undo_mod_action(&msg.channel_login)
+    } else {
+        data_locked
+            .undo_check(&msg.channel_login, &msg.sender.login)
+            .map(|todo| vec![todo])
+    };
+    drop(data_locked);
+
+    let restored = match restored {
+        Some(restored) => restored,
+        None => return Some(lang::NOTHING_TO_UNDO.to_owned()),
+    };
+    log::info!("restored todos: {:?}", restored);
+
+    let reply = match restored.as_slice() {
+        [todo] if !undo_mod_action => {
+            format!("{} {} {}", msg.sender.login, lang::RESTORED_TODO, todo.text)
+        }
+        _ => format!("{} {}", lang::RESTORED_TODOS, restored.len()),
+    };
+    // !todoflush all betrifft alle Channels, deshalb wie beim Flush ein Event pro Channel
+    let mut restored_todos: HashMap<String, Vec<Todo>> = HashMap::new();
+    for todo in restored {
+        restored_todos
+            .entry(todo.channel.clone())
+            .or_default()
+            .push(todo);
+    }
+    for (channel, todos) in restored_todos {
+        send_todo_update(
+            todo_subscribers,
+            TodoUpdate::RestoreTodos {
+                channel,
+                todos,
+                unchecked: !undo_mod_action,
+            },
+        )
+        .await;
+    }
+    Some(reply)
+}
+
+pub async fn handle_edit_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    limits: &TodoLimits,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    let (index, new_text) = match text
+        .as_deref()
+        .map(str::trim)
+        .and_then(|s| s.split_once(' '))
+    {
+        Some((index, new_text)) if !new_text.trim().is_empty() => (index, new_text.trim()),
+        _ => return Some(lang::EDIT_USAGE.to_owned()),
+    };
+    let index = match index.parse::<usize>() {
+        Ok(index) if index > 0 => index - 1,
+        _ => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+    if let Err(exceeded) = limits.check_text(new_text) {
+        return Some(format_limit_reply(exceeded));
+    }
+
+    let edited_todo = match data.lock().await.edit(
+        &msg.channel_login,
+        &msg.sender.login,
+        index,
+        new_text.to_owned(),
+    ) {
+        Some(todo) => todo,
+        None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+    log::info!("edited todo: {:?}", edited_todo);
+
+    let reply = format!(
+        "{} {} {}",
+        msg.sender.login,
+        lang::EDITED_TODO,
+        edited_todo.text
+    );
+    send_todo_update(todo_subscribers, TodoUpdate::EditTodo(edited_todo)).await;
+    Some(reply)
+}
+
+pub fn user_todo_ids(todos: Option<&Vec<Todo>>) -> Vec<u64> {
+    todos
+        .map(|todos| todos.iter().map(|todo| todo.id).collect())
+        .unwrap_or_default()
+}
+
+/// liest "<nummer> <argument>" und macht aus der Nummer einen Index
+fn parse_index_argument(text: Option<&str>) -> Option<(Option<usize>, &str)> {
+    let (index, argument) = text?.trim().split_once(' ')?;
+    let index = index
+        .parse::<usize>()
+        .ok()
+        .filter(|i| *i > 0)
+        .map(|i| i - 1);
+    Some((index, argument.trim()))
+}
+
+/// schickt dem Overlay die neue Reihenfolge und antwortet mit der neuen Liste
+async fn send_reordered(
+    data: &Data,
+    msg: &PrivmsgMessage,
+    todo: Todo,
+    ids: Vec<u64>,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    send_todo_update(todo_subscribers, TodoUpdate::EditTodo(todo)).await;
+    send_todo_update(
+        todo_subscribers,
+        TodoUpdate::ReorderTodos {
+            channel: msg.channel_login.clone(),
+            ids,
+        },
+    )
+    .await;
+    Some(format_message_reply(
+        data.lock()
+            .await
+            .user_todos(&msg.channel_login, &msg.sender.login),
+        None,
+    ))
+}
+
+pub async fn handle_prio_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    let (index, priority) = match parse_index_argument(text.as_deref()) {
+        Some((index, priority)) => match TodoPriority::parse(priority) {
+            Some(priority) => (index, priority),
+            None => return Some(lang::PRIO_USAGE.to_owned()),
+        },
+        None => return Some(lang::PRIO_USAGE.to_owned()),
+    };
+    let changed = match index {
+        Some(index) => {
+            data.lock()
+                .await
+                .set_priority(&msg.channel_login, &msg.sender.login, index, priority)
+        }
+        None => None,
+    };
+    let (todo, ids) = match changed {
+        Some(changed) => changed,
+        None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+    log::info!("changed priority: {:?}", todo);
+    send_reordered(&data, msg, todo, ids, todo_subscribers).await
+}
+
+pub async fn handle_move_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    let (from, to) = match parse_index_argument(text.as_deref()) {
+        Some((from, to)) => match to.parse::<usize>() {
+            Ok(to) if to > 0 => (from, to - 1),
+            _ => return Some(lang::MOVE_USAGE.to_owned()),
+        },
+        None => return Some(lang::MOVE_USAGE.to_owned()),
+    };
+    let moved = match from {
+        Some(from) => data
+            .lock()
+            .await
+            .move_todo(&msg.channel_login, &msg.sender.login, from, to),
+        None => None,
+    };
+    let (todo, ids) = match moved {
+        Some(moved) => moved,
+        None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+    log::info!("moved todo: {:?}", todo);
+    send_reordered(&data, msg, todo, ids, todo_subscribers).await
+}
+
+/// Anzahl der Tags, die !todotags anzeigt
+const TAGS_REPLY_LIMIT: usize = 10;
+
+pub async fn handle_tags_command(data: Data, msg: &PrivmsgMessage) -> Option<String> {
+    let usage = data.lock().await.tag_usage(&msg.channel_login);
+    if usage.is_empty() {
+        return Some(lang::NO_TAGS_YET.to_owned());
+    }
+    Some(format_tag_usage(&usage, TAGS_REPLY_LIMIT))
+}
+
+fn format_tag_usage(usage: &[TagUsage], limit: usize) -> String {
+    let tags_str = usage
+        .iter()
+        .take(limit)
+        .map(|usage| {
+            format!(
+                "#{} ({} {}, {} {})",
+                usage.tag,
+                usage.open,
+                lang::TAG_COUNTS.0,
+                usage.done,
+                lang::TAG_COUNTS.1
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("{} {tags_str}", lang::TAG_USAGE)
+}
+
+/// Anzahl der erledigten todos, die !done anzeigt
+const DONE_REPLY_LIMIT: usize = 5;
+
+pub async fn handle_done_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+) -> Option<String> {
+    // ohne Nutzer werden die zuletzt erledigten todos des ganzen Channels angezeigt
+    let user = text
+        .map(|s| s.replace(['@', ' '], "").to_lowercase())
+        .filter(|s| !s.is_empty());
+    let done = data.lock().await.history(&HistoryQuery {
+        channel: Some(msg.channel_login.clone()),
+        user: user.clone(),
+        limit: Some(DONE_REPLY_LIMIT),
+        ..Default::default()
+    });
+
+    if done.is_empty() {
+        return Some(lang::NOTHING_DONE_YET.to_owned());
+    }
+
+    let mut done_str = String::new();
+    for todo in done {
+        if user.is_none() {
+            done_str.push_str(format!("{}: ", todo.display_name).as_str());
+        }
+        done_str.push_str(todo.text.as_str());
+        done_str.push_str("; ");
+    }
+    Some(format!(
+        "{} {}",
+        lang::RECENTLY_DONE,
+        done_str.trim_end_matches("; ")
+    ))
+}
+
+/// Anzahl der Tags, die !todostats anzeigt
+const STATS_TAGS_LIMIT: usize = 3;
+
+/// !todostats [@nutzer]: Statistik des Nutzers in diesem Channel, ohne Angabe die eigene
+pub async fn handle_stats_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+) -> Option<String> {
+    let user = text
+        .map(|s| s.replace(['@', ' '], "").to_lowercase())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| msg.sender.login.clone());
+    let stats = data
+        .lock()
+        .await
+        .stats(Some(&msg.channel_login), Some(&user));
+    let stats = match stats.into_iter().next() {
+        Some(stats) => stats,
+        None => return Some(lang::NO_STATS_YET.to_owned()),
+    };
+
+    let mut reply = format!(
+        "{}: {} {}, {} {} ({:.0}%), {} {}",
+        stats.display_name,
+        stats.added,
+        lang::STATS_COUNTS.0,
+        stats.completed,
+        lang::STATS_COUNTS.1,
+        stats.completion_rate * 100.0,
+        stats.open,
+        lang::STATS_COUNTS.2
+    );
+    if let Some(secs) = stats.average_completion_secs {
+        reply.push_str(
+            format!(
+                ", {} {}",
+                lang::STATS_AVERAGE,
+                format_duration(TimeDelta::seconds(secs))
+            )
+            .as_str(),
+        );
+    }
+    if !stats.tags.is_empty() {
+        reply.push_str(format!(", {}", format_tag_usage(&stats.tags, STATS_TAGS_LIMIT)).as_str());
+    }
+    Some(reply)
+}
+
+/// Anzahl der Plätze pro Bestenliste, die !todotop anzeigt
+const TOP_REPLY_LIMIT: usize = 3;
+
+/// !todotop: die Nutzer mit den meisten abgehakten todos im Stream, in dieser Woche und insgesamt
+pub async fn handle_top_command(data: Data, msg: &PrivmsgMessage) -> Option<String> {
+    let leaderboard = data
+        .lock()
+        .await
+        .leaderboard(Some(&msg.channel_login), TOP_REPLY_LIMIT);
+    let format_top = |entries: Vec<LeaderboardEntry>| {
+        if entries.is_empty() {
+            return lang::NOBODY_YET.to_owned();
+        }
+        entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                format!(
+                    "{}. {} ({})",
+                    index + 1,
+                    entry.display_name,
+                    entry.completed
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    Some(format!(
+        "{} {} | {} {} | {} {}",
+        lang::LEADERBOARD_PERIODS.0,
+        format_top(leaderboard.stream),
+        lang::LEADERBOARD_PERIODS.1,
+        format_top(leaderboard.week),
+        lang::LEADERBOARD_PERIODS.2,
+        format_top(leaderboard.all_time)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todos(texts: &[&str]) -> Vec<Todo> {
+        texts
+            .iter()
+            .map(|text| Todo {
+                text: text.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_selection_numbers_and_ranges() {
+        let todos = todos(&["a", "b", "c", "d", "e"]);
+        assert_eq!(parse_check_selection(None, &todos), Ok(vec![0]));
+        assert_eq!(parse_check_selection(Some("  "), &todos), Ok(vec![0]));
+        assert_eq!(parse_check_selection(Some("3"), &todos), Ok(vec![2]));
+        assert_eq!(parse_check_selection(Some("1,3"), &todos), Ok(vec![0, 2]));
+        assert_eq!(
+            parse_check_selection(Some("5 1 3"), &todos),
+            Ok(vec![0, 2, 4])
+        );
+        assert_eq!(
+            parse_check_selection(Some("2-4"), &todos),
+            Ok(vec![1, 2, 3])
+        );
+        // gemischt und doppelt, jedes todo kommt nur einmal vor
+        assert_eq!(
+            parse_check_selection(Some("1, 2-3 3"), &todos),
+            Ok(vec![0, 1, 2])
+        );
+        assert_eq!(
+            parse_check_selection(Some("ALL"), &todos),
+            Ok(vec![0, 1, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn check_selection_rejects_invalid_input() {
+        let todos = todos(&["a", "b", "c"]);
+        let usage = Err(lang::CHECK_USAGE.to_owned());
+        assert_eq!(parse_check_selection(Some("0"), &todos), usage);
+        assert_eq!(parse_check_selection(Some("3-1"), &todos), usage);
+        assert_eq!(parse_check_selection(Some("1 abc"), &todos), usage);
+        assert_eq!(parse_check_selection(Some("1-"), &todos), usage);
+        assert_eq!(
+            parse_check_selection(Some("2-4"), &todos),
+            Err(lang::TASK_INDEX_DOESNT_EXIST.to_owned())
+        );
+    }
+
+    #[test]
+    fn check_selection_by_text() {
+        let todos = todos(&["Math homework", "Clean kitchen", "Math exam"]);
+        assert_eq!(
+            parse_check_selection(Some("\"kitchen\""), &todos),
+            Ok(vec![1])
+        );
+        assert_eq!(
+            parse_check_selection(Some("\"mth hw\""), &todos),
+            Ok(vec![0])
+        );
+        assert_eq!(
+            parse_check_selection(Some("\"math"), &todos),
+            Err(format!("{} 1, 3", lang::SEVERAL_MATCHING_TODOS))
+        );
+        assert_eq!(
+            parse_check_selection(Some("\"laundry\""), &todos),
+            Err(lang::NO_MATCHING_TODO.to_owned())
+        );
+        assert_eq!(
+            parse_check_selection(Some("\"\""), &todos),
+            Err(lang::CHECK_USAGE.to_owned())
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("45m"), Some(TimeDelta::minutes(45)));
+        assert_eq!(parse_duration("2h"), Some(TimeDelta::hours(2)));
+        assert_eq!(parse_duration("7d"), Some(TimeDelta::days(7)));
+        assert_eq!(parse_duration("2w"), Some(TimeDelta::weeks(2)));
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("-5m"), None);
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn due_time_from_duration() {
+        let before = Utc::now();
+        let (due_at, text) = parse_due_time("45m Hausaufgaben machen");
+        let due_at = due_at.unwrap();
+        assert_eq!(text, "Hausaufgaben machen");
+        assert!(due_at >= before + TimeDelta::minutes(45));
+        assert!(due_at <= Utc::now() + TimeDelta::minutes(45));
+
+        let (due_at, text) = parse_due_time("2h lernen");
+        assert!(due_at.is_some());
+        assert_eq!(text, "lernen");
+    }
+
+    #[test]
+    fn due_time_from_clock_time() {
+        for input in ["at 18:00 lernen", "um 18:00 lernen", "AT 18:00 lernen"] {
+            let (due_at, text) = parse_due_time(input);
+            let due_at = due_at.unwrap().with_timezone(&Local);
+            assert_eq!(text, "lernen", "{input}");
+            assert_eq!(due_at.format("%H:%M").to_string(), "18:00", "{input}");
+            // die nächste 18:00, also in den nächsten 24 Stunden
+            assert!(due_at > Local::now(), "{input}");
+            assert!(due_at - Local::now() <= TimeDelta::hours(24), "{input}");
+        }
+    }
+
+    #[test]
+    fn invalid_due_time_stays_part_of_the_text() {
+        for input in [
+            "at 25:00 lernen",
+            "um 18:60 lernen",
+            "at abends lernen",
+            "0m lernen",
+            "45m",
+            "at 18:00",
+            "lernen für die Prüfung",
+        ] {
+            assert_eq!(parse_due_time(input), (None, input.to_owned()), "{input}");
+        }
+    }
+
+    #[test]
+    fn subtask_numbers() {
+        assert_eq!(parse_subtask_number("2.1"), Some((1, 0)));
+        assert_eq!(parse_subtask_number(" 10.3 "), Some((9, 2)));
+        assert_eq!(parse_subtask_number("0.1"), None);
+        assert_eq!(parse_subtask_number("2.0"), None);
+        assert_eq!(parse_subtask_number("2"), None);
+        assert_eq!(parse_subtask_number("a.1"), None);
+    }
 }
@@ -0,0 +1,195 @@
+use twitch_irc::message::{PrivmsgMessage, TwitchUserBasics};
+
+use crate::{
+    communication::{send_todo_update, TodoSubscribers, TodoUpdate},
+    config::TodoLimits,
+    lang::lang,
+    moderation::{log_mod_action, ModActionKind, ModLogEntry},
+    todo::FlushScope,
+};
+
+use super::{
+    handle_commands::{format_limit_reply, parse_due_time, user_todo_ids},
+    Data,
+};
+
+/// liest "@nutzer rest" und gibt den login des Nutzers und den Rest zurück
+fn parse_user_argument(text: Option<&str>) -> Option<(String, &str)> {
+    let text = text?.trim();
+    let (user, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let user = user.trim_start_matches('@').to_lowercase();
+    if user.is_empty() {
+        return None;
+    }
+    Some((user, rest.trim()))
+}
+
+/// !todoremove @nutzer <nummer>: ein mod entfernt ein einzelnes todo eines Nutzers
+pub async fn handle_remove_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    is_mod: bool,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    if !is_mod {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+    let (user, index) = match parse_user_argument(text.as_deref()) {
+        Some((user, index)) => match index.parse::<usize>() {
+            Ok(index) if index > 0 => (user, index - 1),
+            _ => return Some(lang::MOD_REMOVE_USAGE.to_owned()),
+        },
+        None => return Some(lang::MOD_REMOVE_USAGE.to_owned()),
+    };
+    let removed = match data.lock().await.remove(&msg.channel_login, &user, index) {
+        Some(todo) => todo,
+        None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+    };
+
+    let reply = format!(
+        "{} {} {user}: {}",
+        msg.sender.login,
+        lang::REMOVED_USER_TODO,
+        removed.text
+    );
+    send_todo_update(
+        todo_subscribers,
+        TodoUpdate::FlushTodos {
+            channel: msg.channel_login.clone(),
+            ids: vec![removed.id],
+        },
+    )
+    .await;
+    log_mod_action(ModLogEntry::new(
+        &msg.channel_login,
+        &msg.sender.login,
+        ModActionKind::Remove,
+        &user,
+        vec![removed],
+    ))
+    .await;
+    Some(reply)
+}
+
+/// !todoclear @nutzer: ein mod entfernt alle offenen todos eines Nutzers im Channel
+pub async fn handle_clear_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    is_mod: bool,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    if !is_mod {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+    let user = match parse_user_argument(text.as_deref()) {
+        Some((user, "")) => user,
+        _ => return Some(lang::MOD_CLEAR_USAGE.to_owned()),
+    };
+    let scope = FlushScope {
+        channel: Some(msg.channel_login.clone()),
+        user: Some(user.clone()),
+        older_than: None,
+    };
+    let cleared = data.lock().await.flush(&scope, &msg.channel_login);
+    if cleared.is_empty() {
+        return Some(lang::USER_HAS_NO_TODOS.to_owned());
+    }
+
+    let reply = format!(
+        "{} {} {user} ({})",
+        msg.sender.login,
+        lang::CLEARED_USER_TODOS,
+        cleared.len()
+    );
+    send_todo_update(
+        todo_subscribers,
+        TodoUpdate::FlushTodos {
+            channel: msg.channel_login.clone(),
+            ids: cleared.iter().map(|todo| todo.id).collect(),
+        },
+    )
+    .await;
+    log_mod_action(ModLogEntry::new(
+        &msg.channel_login,
+        &msg.sender.login,
+        ModActionKind::Clear,
+        &user,
+        cleared,
+    ))
+    .await;
+    Some(reply)
+}
+
+/// !todoadd @nutzer <text>: ein mod trägt ein todo für einen Nutzer ein, es gelten die gleichen Limits wie bei !todo
+pub async fn handle_mod_add_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    is_mod: bool,
+    limits: &TodoLimits,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    if !is_mod {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+    let (user, text) = match parse_user_argument(text.as_deref()) {
+        Some((user, text)) if !text.is_empty() => (user, text),
+        _ => return Some(lang::MOD_ADD_USAGE.to_owned()),
+    };
+    let (due_at, text) = parse_due_time(text);
+
+    let mut data_locked = data.lock().await;
+    if let Err(exceeded) = limits.check(&data_locked, &msg.channel_login, &user, &text) {
+        return Some(format_limit_reply(exceeded));
+    }
+    // id und Anzeigename kennt der Bot nur von todos, die der Nutzer selbst erstellt hat
+    let author = match data_locked
+        .user_todos(&msg.channel_login, &user)
+        .and_then(|todos| todos.first())
+    {
+        Some(todo) => TwitchUserBasics {
+            id: todo.user_id.clone(),
+            login: user.clone(),
+            name: todo.display_name.clone(),
+        },
+        None => TwitchUserBasics {
+            id: String::new(),
+            login: user.clone(),
+            name: user.clone(),
+        },
+    };
+    let todo = data_locked.add(&msg.channel_login, &author, text, due_at);
+    let reorder = data_locked
+        .is_reordered(&todo)
+        .then(|| user_todo_ids(data_locked.user_todos(&msg.channel_login, &user)));
+    drop(data_locked);
+
+    let reply = format!(
+        "{} {} {user}: {}",
+        msg.sender.login,
+        lang::ADDED_USER_TODO,
+        todo.text
+    );
+    send_todo_update(todo_subscribers, TodoUpdate::AddTodo(todo.clone())).await;
+    if let Some(ids) = reorder {
+        send_todo_update(
+            todo_subscribers,
+            TodoUpdate::ReorderTodos {
+                channel: msg.channel_login.clone(),
+                ids,
+            },
+        )
+        .await;
+    }
+    log_mod_action(ModLogEntry::new(
+        &msg.channel_login,
+        &msg.sender.login,
+        ModActionKind::Add,
+        &user,
+        vec![todo],
+    ))
+    .await;
+    Some(reply)
+}
@@ -0,0 +1,153 @@
+use twitch_irc::message::{PrivmsgMessage, TwitchUserBasics};
+
+use crate::{
+    communication::{send_todo_update, TodoSubscribers, TodoUpdate},
+    config::{RetentionRules, TodoLimits},
+    lang::lang,
+    retention::archive_channel,
+    todo::STREAM_TODOS,
+};
+
+use super::{handle_commands::format_limit_reply, Data};
+
+const STREAM_TODO_CHECK: &str = "check";
+const STREAM_TODO_REMOVE: &str = "remove";
+
+/// !streamtodos: die Liste des Streamers, die kann jeder sehen
+pub async fn handle_list_stream_todos(data: Data, msg: &PrivmsgMessage) -> Option<String> {
+    let data = data.lock().await;
+    let todos = match data.user_todos(&msg.channel_login, STREAM_TODOS) {
+        Some(todos) if !todos.is_empty() => todos,
+        _ => return Some(lang::NO_STREAM_TODOS.to_owned()),
+    };
+    let todos_str = todos
+        .iter()
+        .enumerate()
+        .map(|(index, todo)| format!("({}) {}", index + 1, todo.text_with_tags()))
+        .collect::<Vec<String>>()
+        .join(" ");
+    Some(format!("{} {todos_str}", lang::STREAM_TODOS_HEADER))
+}
+
+/// !streamtodo <text>, !streamtodo check <nummer> oder !streamtodo remove <nummer>
+/// ändern dürfen die Liste nur der Streamer und die mods
+pub async fn handle_stream_todo_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    can_manage: bool,
+    limits: &TodoLimits,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    if !can_manage {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+    let text = match text.as_deref().map(str::trim) {
+        Some(text) if !text.is_empty() => text.to_owned(),
+        _ => return Some(lang::STREAM_TODO_USAGE.to_owned()),
+    };
+    let (action, argument) = text.split_once(' ').unwrap_or((&text, ""));
+    match action.to_lowercase().as_str() {
+        STREAM_TODO_CHECK | STREAM_TODO_REMOVE => {
+            let index = match argument.trim().parse::<usize>() {
+                Ok(index) if index > 0 => index - 1,
+                _ => return Some(lang::STREAM_TODO_USAGE.to_owned()),
+            };
+            let is_check = action.eq_ignore_ascii_case(STREAM_TODO_CHECK);
+            let mut data_locked = data.lock().await;
+            let todo = match is_check {
+                true => data_locked.check(&msg.channel_login, STREAM_TODOS, index),
+                false => data_locked.remove(&msg.channel_login, STREAM_TODOS, index),
+            };
+            drop(data_locked);
+            let todo = match todo {
+                Some(todo) => todo,
+                None => return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned()),
+            };
+            log::info!("{} stream todo: {:?}", action, todo);
+
+            let reply = match is_check {
+                true => format!(
+                    "{} {} {} {}",
+                    msg.sender.login,
+                    lang::FINISHED_TODO.0,
+                    todo.text,
+                    lang::FINISHED_TODO.1
+                ),
+                false => format!(
+                    "{} {} {}",
+                    msg.sender.login,
+                    lang::REMOVED_STREAM_TODO,
+                    todo.text
+                ),
+            };
+            // ein entferntes Ziel ist nicht geschafft und darf im Overlay nicht so aussehen
+            let channel = msg.channel_login.clone();
+            let ids = vec![todo.id];
+            let todo_update = match is_check {
+                true => TodoUpdate::CheckTodo { channel, ids },
+                false => TodoUpdate::FlushTodos { channel, ids },
+            };
+            send_todo_update(todo_subscribers, todo_update).await;
+            Some(reply)
+        }
+        _ => {
+            if let Err(exceeded) = limits.check_text(&text) {
+                return Some(format_limit_reply(exceeded));
+            }
+            // die Liste gehört dem Channel, als Anzeigename bleibt stehen, wer den Eintrag gemacht hat
+            let author = TwitchUserBasics {
+                id: msg.sender.id.clone(),
+                login: STREAM_TODOS.to_owned(),
+                name: msg.sender.name.clone(),
+            };
+            let todo = data
+                .lock()
+                .await
+                .add(&msg.channel_login, &author, text, None);
+            log::info!("added stream todo: {:?}", todo);
+
+            let reply = format!(
+                "{} {} {}",
+                msg.sender.login,
+                lang::ADDED_STREAM_TODO,
+                todo.text
+            );
+            send_todo_update(todo_subscribers, TodoUpdate::AddTodo(todo)).await;
+            Some(reply)
+        }
+    }
+}
+
+/// !streamend: der Stream ist vorbei, die Bestenliste für den Stream fängt von vorne an
+/// mit archive_on_stream_end werden außerdem die offenen todos archiviert
+pub async fn handle_stream_end_command(
+    data: Data,
+    msg: &PrivmsgMessage,
+    can_manage: bool,
+    rules: &RetentionRules,
+    todo_subscribers: &TodoSubscribers,
+) -> Option<String> {
+    if !can_manage {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+    data.lock().await.end_stream(&msg.channel_login);
+    send_todo_update(
+        todo_subscribers,
+        TodoUpdate::StreamEnded {
+            channel: msg.channel_login.clone(),
+        },
+    )
+    .await;
+    if !rules.archive_on_stream_end {
+        return Some(lang::STREAM_ENDED.to_owned());
+    }
+    match archive_channel(&data, &msg.channel_login, todo_subscribers).await {
+        Ok(0) => Some(lang::NOTHING_TO_ARCHIVE.to_owned()),
+        Ok(count) => Some(format!("{} ({count})", lang::ARCHIVED_TODOS)),
+        Err(e) => {
+            log::error!("Error when archiving todos: {e}");
+            Some(lang::ERROR_WHEN_ARCHIVING.to_owned())
+        }
+    }
+}
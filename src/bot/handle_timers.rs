@@ -0,0 +1,124 @@
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::{lang::lang, timer::Timers};
+
+use super::Data;
+
+/// so lange dauert ein !focus ohne Angabe
+const DEFAULT_FOCUS_MINUTES: u32 = 25;
+const DEFAULT_POMODORO_MINUTES: (u32, u32) = (25, 5);
+/// längster erlaubter Timer
+const MAX_TIMER_MINUTES: u32 = 240;
+const TIMER_START: &str = "start";
+const TIMER_STOP: &str = "stop";
+
+/// liest die Minuten eines Timers, None wenn sie fehlen, Err wenn sie ungültig sind
+fn parse_minutes(text: Option<&str>) -> Result<Option<u32>, ()> {
+    match text {
+        None => Ok(None),
+        Some(text) => match text.trim_end_matches('m').parse::<u32>() {
+            Ok(minutes) if minutes > 0 && minutes <= MAX_TIMER_MINUTES => Ok(Some(minutes)),
+            _ => Err(()),
+        },
+    }
+}
+
+/// !focus [minuten] [todo nummer] oder !focus stop
+pub async fn handle_focus_command(
+    text: Option<String>,
+    data: Data,
+    msg: &PrivmsgMessage,
+    timers: &Timers,
+) -> Option<String> {
+    let text = text.unwrap_or_default();
+    let args: Vec<&str> = text.split_whitespace().collect();
+
+    if args
+        .first()
+        .is_some_and(|arg| arg.eq_ignore_ascii_case(TIMER_STOP))
+    {
+        return match timers
+            .stop_focus(&msg.channel_login, &msg.sender.login)
+            .await
+        {
+            Some(_) => Some(lang::FOCUS_STOPPED.to_owned()),
+            None => Some(lang::NO_FOCUS_RUNNING.to_owned()),
+        };
+    }
+
+    let minutes = match parse_minutes(args.first().copied()) {
+        Ok(minutes) => minutes.unwrap_or(DEFAULT_FOCUS_MINUTES),
+        Err(_) => return Some(lang::FOCUS_USAGE.to_owned()),
+    };
+    // ohne Nummer gehört die Session zum ersten todo des Nutzers (falls es eins gibt)
+    let index = match args.get(1).map(|index| index.parse::<usize>()) {
+        None => 0,
+        Some(Ok(index)) if index > 0 => index - 1,
+        Some(_) => return Some(lang::FOCUS_USAGE.to_owned()),
+    };
+    let todo = data
+        .lock()
+        .await
+        .user_todos(&msg.channel_login, &msg.sender.login)
+        .and_then(|todos| todos.get(index))
+        .cloned();
+    if todo.is_none() && args.len() > 1 {
+        return Some(lang::TASK_INDEX_DOESNT_EXIST.to_owned());
+    }
+
+    let timer = timers
+        .start_focus(&msg.channel_login, &msg.sender, todo.as_ref(), minutes)
+        .await;
+    log::info!("started focus session: {:?}", timer);
+    Some(match todo {
+        Some(todo) => format!(
+            "{} {minutes} {}: {}",
+            lang::FOCUS_STARTED.0,
+            lang::FOCUS_STARTED.1,
+            todo.text
+        ),
+        None => format!(
+            "{} {minutes} {}",
+            lang::FOCUS_STARTED.0,
+            lang::FOCUS_STARTED.1
+        ),
+    })
+}
+
+/// !pomodoro start [arbeit] [pause] oder !pomodoro stop, nur für mods
+pub async fn handle_pomodoro_command(
+    text: Option<String>,
+    msg: &PrivmsgMessage,
+    is_mod: bool,
+    timers: &Timers,
+) -> Option<String> {
+    if !is_mod {
+        return Some(lang::NO_PERMISSION.to_owned());
+    }
+    let text = text.unwrap_or_default();
+    let args: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+
+    match args.first().map(String::as_str) {
+        Some(TIMER_START) => {
+            let work_minutes = parse_minutes(args.get(1).map(String::as_str));
+            let break_minutes = parse_minutes(args.get(2).map(String::as_str));
+            let (work_minutes, break_minutes) = match (work_minutes, break_minutes) {
+                (Ok(work_minutes), Ok(break_minutes)) => (
+                    work_minutes.unwrap_or(DEFAULT_POMODORO_MINUTES.0),
+                    break_minutes.unwrap_or(DEFAULT_POMODORO_MINUTES.1),
+                ),
+                _ => return Some(lang::POMODORO_USAGE.to_owned()),
+            };
+            let timer = timers
+                .start_pomodoro(&msg.channel_login, work_minutes, break_minutes)
+                .await;
+            log::info!("started pomodoro: {:?}", timer);
+            Some(format!("{} {work_minutes} min", lang::POMODORO_WORK))
+        }
+        Some(TIMER_STOP) => match timers.stop_pomodoro(&msg.channel_login).await {
+            Some(_) => Some(lang::POMODORO_STOPPED.to_owned()),
+            None => Some(lang::NO_POMODORO_RUNNING.to_owned()),
+        },
+        _ => Some(lang::POMODORO_USAGE.to_owned()),
+    }
+}
@@ -9,7 +9,10 @@ use twitch_irc::{
     TwitchIRCClient,
 };
 
-use crate::file_names;
+use crate::{
+    file_names,
+    migration::{migrate_channels, CHANNELS_HEADER, CHANNELS_VERSION},
+};
 
 pub struct ChannelJoiner<T, C>
 where
@@ -35,13 +38,16 @@ where
 
     pub async fn load(client: Arc<Mutex<TwitchIRCClient<T, C>>>) -> Self {
         let mut channels = BTreeSet::new();
-        for channel in tokio::fs::read_to_string(file_names::CHANNELS_TO_WATCH)
+        let content = tokio::fs::read_to_string(file_names::CHANNELS_TO_WATCH)
             .await
+            .unwrap();
+        for channel in migrate_channels(file_names::CHANNELS_TO_WATCH, content)
             .unwrap()
-            .split("\n")
+            .split('\n')
         {
             let channel = channel.trim().replace(",", "");
-            if channel != "".to_owned() {
+            // Zeilen mit # sind Kommentare (bspw. die Versionsnummer)
+            if !channel.is_empty() && !channel.starts_with('#') {
                 channels.insert(channel.clone());
                 client.lock().await.join(channel).unwrap();
             }
@@ -51,16 +57,14 @@ where
     }
 
     pub async fn update_channels(&mut self, channels: Vec<String>) -> anyhow::Result<()> {
-        let new_channels = BTreeSet::from_iter(channels.clone().into_iter());
+        let new_channels = BTreeSet::from_iter(channels);
         let channels_to_disconnect = self
             .channels
             .difference(&new_channels)
-            .map(|s| s.clone())
+            .cloned()
             .collect::<Vec<String>>();
-        let channels_to_connect: Vec<String> = new_channels
-            .difference(&self.channels)
-            .map(|s| s.clone())
-            .collect();
+        let channels_to_connect: Vec<String> =
+            new_channels.difference(&self.channels).cloned().collect();
 
         for channel in channels_to_connect {
             match self.client.lock().await.join(channel.clone()) {
@@ -87,15 +91,15 @@ where
                 .unwrap();
         }
 
-        return self.save_to_file().await;
+        self.save_to_file().await
     }
 
     pub fn channels(&self) -> Vec<String> {
-        self.channels.iter().map(|s| s.clone()).collect()
+        self.channels.iter().cloned().collect()
     }
 
     pub async fn save_to_file(&self) -> Result<()> {
-        let mut out = String::new();
+        let mut out = format!("{CHANNELS_HEADER}{CHANNELS_VERSION}\n");
         for channel in &self.channels {
             out.push_str(channel.as_str());
             out.push('\n');
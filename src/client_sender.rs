@@ -46,28 +46,95 @@ where
             let (msg, _) = join!(send, sleep);
 
             if let Some(msg) = msg {
-                let message = format!(
-                    "@{} {}",
-                    match msg.reciever {
-                        Some(r) => r,
-                        None => String::new(),
-                    },
-                    msg.message
-                );
+                // lange Antworten (z.B. !todohelp) werden auf mehrere Nachrichten verteilt
+                for (index, part) in split_message(&msg.message).into_iter().enumerate() {
+                    if index > 0 {
+                        tokio::time::sleep(duration).await;
+                    }
+                    // Nachrichten an den ganzen Channel (z.B. !pomodoro) haben keinen Empfänger
+                    let message = match &msg.reciever {
+                        Some(reciever) => format!("@{} {}", reciever, part),
+                        None => part,
+                    };
 
-                self.client
-                    .lock()
-                    .await
-                    .privmsg(msg.channel, message)
-                    .await
-                    .unwrap();
+                    self.client
+                        .lock()
+                        .await
+                        .privmsg(msg.channel.clone(), message)
+                        .await
+                        .unwrap();
+                }
             }
         }
     }
 }
 
+/// Twitch erlaubt 500 Zeichen pro Nachricht, der Rest ist Platz für den Namen des Empfängers
+const MAX_MESSAGE_LENGTH: usize = 450;
+
+/// teilt eine Antwort an Zeilen und Leerzeichen in Nachrichten auf, die Twitch noch annimmt
+/// Zeilenumbrüche gehen per IRC nicht, die Zeilen werden mit Leerzeichen aneinandergehängt
+/// Wörter, die allein schon zu lang sind, werden mitten im Wort getrennt
+fn split_message(message: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let words = message.split_whitespace().flat_map(|word| {
+        let chars: Vec<char> = word.chars().collect();
+        chars
+            .chunks(MAX_MESSAGE_LENGTH)
+            .map(|chunk| chunk.iter().collect::<String>())
+            .collect::<Vec<String>>()
+    });
+    for word in words {
+        if !current.is_empty()
+            && current.chars().count() + word.chars().count() + 1 > MAX_MESSAGE_LENGTH
+        {
+            parts.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    if !current.is_empty() || parts.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
 pub fn spawn_sender_worker<T: Transport, C: LoginCredentials>(
     client_sender: ClientSender<T, C>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move { client_sender.start().await })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_message_stays_one_part() {
+        assert_eq!(
+            split_message("hallo  welt\nneue Zeile"),
+            ["hallo welt neue Zeile"]
+        );
+        assert_eq!(split_message(""), [""]);
+    }
+
+    #[test]
+    fn splits_at_spaces() {
+        let word = "a".repeat(300);
+        let parts = split_message(&format!("{word} {word}"));
+        assert_eq!(parts, [word.clone(), word]);
+    }
+
+    #[test]
+    fn hard_splits_overlong_words() {
+        let parts = split_message(&"ä".repeat(MAX_MESSAGE_LENGTH * 2 + 10));
+        assert_eq!(parts.len(), 3);
+        assert!(parts
+            .iter()
+            .all(|part| part.chars().count() <= MAX_MESSAGE_LENGTH));
+        assert_eq!(parts[2].chars().count(), 10);
+    }
+}
@@ -1,3 +1,12 @@
+use std::sync::Arc;
+
+use tokio::sync::{
+    mpsc::{error::TrySendError, Sender},
+    Mutex,
+};
+
+use crate::todo::Todo;
+
 pub struct BotMessage {
     pub reciever: Option<String>,
     pub message: String,
@@ -6,10 +15,81 @@ pub struct BotMessage {
 
 #[derive(Clone)]
 pub enum TodoUpdate {
-    AddTodo {
-        user: String,
-        todo_message: String,
-        uuid: u64,
+    AddTodo(Todo),
+    /// abgehakte todos, bei !check all oder mehreren Nummern alle in einem Update
+    CheckTodo {
+        channel: String,
+        ids: Vec<u64>,
+    },
+    /// das todo wurde geändert und behält seine id
+    EditTodo(Todo),
+    /// das todo ist jetzt in Arbeit (!todostart), ein vorher gestartetes todo des Nutzers kommt als EditTodo
+    StartTodo(Todo),
+    /// die Liste eines Nutzers hat eine neue Reihenfolge (!todoprio, !todomove)
+    ReorderTodos {
+        channel: String,
+        ids: Vec<u64>,
+    },
+    /// per /import_todos hinzugefügte todos eines Channels, als ein Update statt einem pro todo
+    ImportTodos {
+        channel: String,
+        todos: Vec<Todo>,
     },
-    CheckTodo(u64),
+    /// mit !todoundo zurückgeholte todos, sie kommen wieder in die Listen
+    RestoreTodos {
+        channel: String,
+        todos: Vec<Todo>,
+        /// die todos waren abgehakt (und zählen jetzt nicht mehr als erledigt), sonst von einem mod gelöscht
+        unchecked: bool,
+    },
+    /// !streamend: die Bestenliste für den Stream fängt von vorne an
+    StreamEnded {
+        channel: String,
+    },
+    /// ein mod hat mit !todoflush todos gelöscht
+    FlushTodos {
+        channel: String,
+        ids: Vec<u64>,
+    },
+}
+
+impl TodoUpdate {
+    pub fn channel(&self) -> &String {
+        match self {
+            TodoUpdate::AddTodo(todo)
+            | TodoUpdate::EditTodo(todo)
+            | TodoUpdate::StartTodo(todo) => &todo.channel,
+            TodoUpdate::CheckTodo { channel, .. } => channel,
+            TodoUpdate::ReorderTodos { channel, .. } => channel,
+            TodoUpdate::ImportTodos { channel, .. } => channel,
+            TodoUpdate::RestoreTodos { channel, .. } => channel,
+            TodoUpdate::StreamEnded { channel } => channel,
+            TodoUpdate::FlushTodos { channel, .. } => channel,
+        }
+    }
+}
+
+/// offene Verbindungen eines Overlays, jede bekommt alle Änderungen
+pub type Subscribers<T> = Arc<Mutex<Vec<Sender<T>>>>;
+
+/// so viele Updates kann eine Verbindung puffern, die Overlays holen sie nur einmal pro Sekunde ab
+pub const SUBSCRIBER_BUFFER: usize = 64;
+
+/// alle offenen Verbindungen der Overlays (/todos_sse)
+pub type TodoSubscribers = Subscribers<TodoUpdate>;
+
+/// schickt die Änderung an alle Overlays und entfernt geschlossene Verbindungen
+pub async fn send_todo_update(todo_subscribers: &TodoSubscribers, todo_update: TodoUpdate) {
+    send_to_subscribers(todo_subscribers, todo_update).await
+}
+
+/// wartet nicht auf die Overlays, sonst hält ein langsames Overlay den Bot und alle anderen Verbindungen auf
+pub async fn send_to_subscribers<T: Clone>(subscribers: &Subscribers<T>, update: T) {
+    let mut subscriber_lock = subscribers.lock().await;
+    for subscriber in subscriber_lock.iter() {
+        if let Err(TrySendError::Full(_)) = subscriber.try_send(update.clone()) {
+            log::warn!("overlay connection is not keeping up, dropping update");
+        }
+    }
+    subscriber_lock.retain(|e| !e.is_closed());
 }
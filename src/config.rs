@@ -1,15 +1,20 @@
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
+use chrono::{DateTime, Local, NaiveTime, TimeDelta, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use twitch_irc::login::{CredentialsPair, StaticLoginCredentials};
 
 use crate::{
     bot::Data,
-    file_names::{CREDENTIALS, MODS, TODO_SAVE},
+    file_names::{CONFIG, CREDENTIALS, MODS},
+    migration::{migrate_mods, MODS_VERSION},
+    storage::{open_storage, StorageKind, TodoData},
+    todo::TodoStore,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -25,37 +30,173 @@ impl CredentialsFile {
     }
 }
 
-impl Into<StaticLoginCredentials> for CredentialsFile {
-    fn into(self) -> StaticLoginCredentials {
+impl From<CredentialsFile> for StaticLoginCredentials {
+    fn from(file: CredentialsFile) -> Self {
         StaticLoginCredentials {
             credentials: CredentialsPair {
-                login: self.login,
-                token: Some(self.token),
+                login: file.login,
+                token: Some(file.token),
             },
         }
     }
 }
 
-pub async fn load_data() -> anyhow::Result<Data> {
-    let save_file = tokio::fs::read_to_string(TODO_SAVE).await;
+/// allgemeine Einstellungen aus der config.json, fehlende Werte werden mit Standardwerten belegt
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    pub storage: StorageKind,
+    /// spätestens nach so vielen Sekunden werden Änderungen automatisch gespeichert
+    pub autosave_interval_secs: u64,
+    /// wie viele alte Stände der todos im backups Ordner behalten werden
+    pub backup_count: usize,
+    /// frühestens nach so vielen Sekunden wird beim Speichern ein neues backup angelegt
+    pub backup_interval_secs: u64,
+    /// so lange können !check und !todoflush mit !todoundo rückgängig gemacht werden
+    pub undo_window_secs: u64,
+    /// Grenzen für neue todos in allen Channels, die keine eigenen haben
+    pub limits: TodoLimits,
+    /// eigene Grenzen für einzelne Channels, ersetzen limits komplett
+    pub channel_limits: HashMap<String, TodoLimits>,
+    /// wann offene todos automatisch entfernt werden, für alle Channels ohne eigene Regeln
+    pub retention: RetentionRules,
+    /// eigene Regeln für einzelne Channels, ersetzen retention komplett
+    pub channel_retention: HashMap<String, RetentionRules>,
+}
+
+/// Regeln, nach denen offene todos der Zuschauer automatisch entfernt werden, standardmäßig ist alles aus
+/// die Liste des Streamers (!streamtodo) ist davon nicht betroffen
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionRules {
+    /// offene todos werden nach so vielen Tagen gelöscht
+    pub expire_after_days: Option<u32>,
+    /// jeden Tag zu dieser Uhrzeit (lokale Zeit, z.B. "05:00") werden alle Listen geleert
+    pub daily_reset_at: Option<NaiveTime>,
+    /// bei !streamend werden die offenen todos in den archive Ordner geschrieben und die Listen geleert
+    pub archive_on_stream_end: bool,
+}
+
+impl RetentionRules {
+    /// todos, die vor diesem Zeitpunkt erstellt wurden, sind nach den Regeln abgelaufen
+    /// beim täglichen Reset ist das der letzte Reset, so werden auch Resets nachgeholt, die der Bot verpasst hat
+    pub fn cutoff(&self, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+        let expired = self
+            .expire_after_days
+            .map(|days| now.to_utc() - TimeDelta::days(i64::from(days)));
+        let last_reset = self.daily_reset_at.and_then(|time| {
+            let today = now.date_naive().and_time(time);
+            let last_reset = match Local.from_local_datetime(&today).earliest() {
+                Some(reset) if reset <= now => reset,
+                _ => Local
+                    .from_local_datetime(&(today - TimeDelta::days(1)))
+                    .earliest()?,
+            };
+            Some(last_reset.to_utc())
+        });
+        expired.max(last_reset)
+    }
+}
+
+/// Grenzen für !todo, None heißt unbegrenzt
+/// standardmäßig gibt es keine Grenzen, damit sich bei bestehenden Installationen ohne limits nichts ändert
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TodoLimits {
+    /// offene todos pro Nutzer
+    pub max_todos_per_user: Option<usize>,
+    /// Zeichen pro todo
+    pub max_text_length: Option<usize>,
+    /// offene todos im ganzen Channel
+    pub max_todos_per_channel: Option<usize>,
+}
+
+/// welche Grenze ein neues todo überschreiten würde, mit dem erlaubten Wert
+#[derive(Clone, Copy, Debug)]
+pub enum LimitExceeded {
+    UserTodos(usize),
+    TextLength(usize),
+    ChannelTodos(usize),
+}
 
-    let file_string = match save_file {
-        Ok(r) => r,
-        Err(_) => return Ok(Arc::new(Mutex::new(HashMap::new()))),
-    };
+impl TodoLimits {
+    pub fn check_text(&self, text: &str) -> Result<(), LimitExceeded> {
+        match self.max_text_length {
+            Some(max) if text.chars().count() > max => Err(LimitExceeded::TextLength(max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check(
+        &self,
+        store: &TodoStore,
+        channel: &str,
+        user: &str,
+        text: &str,
+    ) -> Result<(), LimitExceeded> {
+        self.check_text(text)?;
+        if let Some(max) = self.max_todos_per_user {
+            if store.user_todos(channel, user).map_or(0, Vec::len) >= max {
+                return Err(LimitExceeded::UserTodos(max));
+            }
+        }
+        if let Some(max) = self.max_todos_per_channel {
+            if store.channel_count(channel) >= max {
+                return Err(LimitExceeded::ChannelTodos(max));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            storage: StorageKind::default(),
+            autosave_interval_secs: 300,
+            backup_count: 10,
+            backup_interval_secs: 3600,
+            undo_window_secs: 300,
+            limits: TodoLimits::default(),
+            channel_limits: HashMap::new(),
+            retention: RetentionRules::default(),
+            channel_retention: HashMap::new(),
+        }
+    }
+}
+
+impl BotConfig {
+    pub fn limits_for(&self, channel: &str) -> &TodoLimits {
+        self.channel_limits.get(channel).unwrap_or(&self.limits)
+    }
+
+    pub fn retention_for(&self, channel: &str) -> &RetentionRules {
+        self.channel_retention
+            .get(channel)
+            .unwrap_or(&self.retention)
+    }
+
+    pub async fn load() -> Self {
+        match tokio::fs::read_to_string(CONFIG).await {
+            Ok(r) => serde_json::from_str(r.as_str()).unwrap(),
+            Err(e) => {
+                log::warn!("no config file found, using default config: {e}");
+                Self::default()
+            }
+        }
+    }
+}
 
-    Ok(Arc::new(Mutex::new(serde_json::from_str(
-        file_string.as_str(),
+pub async fn load_data(config: &BotConfig) -> anyhow::Result<Data> {
+    let storage = open_storage(config)?;
+    Ok(Arc::new(Mutex::new(TodoData::load(
+        storage,
+        Duration::from_secs(config.undo_window_secs),
     )?)))
 }
 
 pub async fn save_data(data: &Data) -> anyhow::Result<()> {
-    // das Datenobjekt muss in eine einfache Hashmap verwandelt werden, damit serde_json diesen in json verwandeln kann
-    let data = data.lock().await.clone();
-    let data: HashMap<&String, &Vec<String>> = data.iter().filter(|(_, v)| v.len() > 0).collect();
-    let file_content = serde_json::to_string(&data)?;
-    tokio::fs::write(TODO_SAVE, &file_content.as_str()).await?;
-    Ok(())
+    data.lock().await.save()
 }
 
 pub struct ModSet {
@@ -65,11 +206,7 @@ pub struct ModSet {
 impl ModSet {
     pub async fn load() -> Self {
         let set = match tokio::fs::read_to_string(MODS).await {
-            Ok(r) => HashSet::from_iter(
-                serde_json::from_str::<Vec<String>>(r.as_str())
-                    .unwrap()
-                    .into_iter(),
-            ),
+            Ok(r) => HashSet::from_iter(migrate_mods(MODS, r.as_str()).unwrap()),
             Err(e) => {
                 log::error!("error while loading mods file: {e}");
                 HashSet::new()
@@ -80,13 +217,16 @@ impl ModSet {
 
     pub async fn save(&self) -> anyhow::Result<()> {
         let v: Vec<&String> = self.set.iter().collect();
-        let file_content = serde_json::to_string(&v)?;
+        let file_content = serde_json::to_string(&serde_json::json!({
+            "version": MODS_VERSION,
+            "mods": v,
+        }))?;
         tokio::fs::write(MODS, file_content).await?;
         Ok(())
     }
 
     pub fn update(&mut self, mods: Vec<String>) {
         self.set.clear();
-        self.set = HashSet::from_iter(mods.into_iter());
+        self.set = HashSet::from_iter(mods);
     }
 }
@@ -1,7 +1,14 @@
-
-/// getrennte file für alle Dateinamen
+//! getrennte file für alle Dateinamen
 
 pub const TODO_SAVE: &str = "./todos.json";
+pub const TODO_JOURNAL: &str = "./todos.journal";
+pub const TODO_AUDIT_LOG: &str = "./todos.audit.log";
+pub const MODERATION_LOG: &str = "./moderation.log";
+pub const TODO_DATABASE: &str = "./todos.sqlite";
+pub const TODO_BACKUPS: &str = "./backups";
+pub const TODO_ARCHIVE: &str = "./archive";
+pub const STREAMS: &str = "./streams.json";
 pub const CREDENTIALS: &str = "./credentials.json";
 pub const CHANNELS_TO_WATCH: &str = "./channels.csv";
 pub const MODS: &str = "./mods.json";
+pub const CONFIG: &str = "./config.json";
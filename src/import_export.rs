@@ -0,0 +1,226 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::todo::{parse_tags, Subtask, Todo, STREAM_TODOS};
+
+/// Formate für den Import und Export der offenen todos
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferFormat {
+    Json,
+    Csv,
+    #[serde(alias = "md")]
+    Markdown,
+}
+
+impl TransferFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "application/json",
+            TransferFormat::Csv => "text/csv; charset=utf-8",
+            TransferFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "json",
+            TransferFormat::Csv => "csv",
+            TransferFormat::Markdown => "md",
+        }
+    }
+}
+
+/// ein todo, wie es in einer csv Zeile steht oder importiert wird
+/// Channel und Nutzer dürfen fehlen, dann werden die Werte aus der Anfrage genommen
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferTodo {
+    #[serde(default)]
+    pub channel: String,
+    #[serde(default)]
+    pub user: String,
+    #[serde(default)]
+    pub display_name: String,
+    pub text: String,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// nur in json und Markdown, csv kennt keine Unterpunkte
+    #[serde(default, skip_serializing)]
+    pub subtasks: Vec<Subtask>,
+    /// nur in json, in csv und Markdown stehen die Tags im Text
+    #[serde(default, skip_serializing)]
+    pub tags: Vec<String>,
+}
+
+pub fn export_todos(todos: &[Todo], format: TransferFormat) -> anyhow::Result<String> {
+    Ok(match format {
+        TransferFormat::Json => serde_json::to_string_pretty(todos)?,
+        TransferFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for todo in todos {
+                writer.serialize(TransferTodo {
+                    channel: todo.channel.clone(),
+                    user: todo.user.clone(),
+                    display_name: todo.display_name.clone(),
+                    text: todo.text_with_tags(),
+                    created_at: Some(todo.created_at),
+                    subtasks: vec![],
+                    tags: vec![],
+                })?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
+        TransferFormat::Markdown => {
+            // # channel, ## nutzer, darunter die todos als checkliste
+            let mut out = String::new();
+            let mut last: Option<(&String, &String)> = None;
+            for todo in todos {
+                if last.is_none_or(|(channel, _)| channel != &todo.channel) {
+                    writeln!(out, "# {}\n", todo.channel)?;
+                    last = None;
+                }
+                if last.is_none_or(|(_, user)| user != &todo.user) {
+                    writeln!(out, "## {}\n", todo.user)?;
+                }
+                writeln!(out, "- [ ] {}", todo.text_with_tags().replace('\n', " "))?;
+                for subtask in todo.subtasks.iter() {
+                    let mark = if subtask.done { 'x' } else { ' ' };
+                    writeln!(out, "  - [{mark}] {}", subtask.text.replace('\n', " "))?;
+                }
+                last = Some((&todo.channel, &todo.user));
+            }
+            out
+        }
+    })
+}
+
+pub fn parse_todos(content: &str, format: TransferFormat) -> anyhow::Result<Vec<TransferTodo>> {
+    Ok(match format {
+        TransferFormat::Json => serde_json::from_str(content)?,
+        TransferFormat::Csv => csv::Reader::from_reader(content.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()?,
+        TransferFormat::Markdown => {
+            let mut todos: Vec<TransferTodo> = vec![];
+            let mut channel = String::new();
+            let mut user = String::new();
+            // eingerückte Einträge sind Unterpunkte, sie gehören zum letzten importierten todo
+            let mut last_imported = false;
+            for raw_line in content.lines() {
+                let line = raw_line.trim();
+                let is_nested = raw_line.starts_with([' ', '\t']);
+                if is_nested {
+                    if let Some((done, text)) = parse_checklist_item(line) {
+                        if let (true, Some(todo)) = (last_imported, todos.last_mut()) {
+                            todo.subtasks.push(Subtask {
+                                text: text.to_owned(),
+                                done,
+                            });
+                        }
+                    }
+                } else if let Some(c) = line.strip_prefix("# ") {
+                    channel = c.trim().to_owned();
+                    user.clear();
+                } else if let Some(u) = line.strip_prefix("## ") {
+                    user = u.trim().to_owned();
+                } else if let Some((done, text)) = parse_checklist_item(line) {
+                    // abgehakte Einträge (- [x]) werden nicht importiert
+                    last_imported = !done;
+                    if !done {
+                        todos.push(TransferTodo {
+                            channel: channel.clone(),
+                            user: user.clone(),
+                            display_name: String::new(),
+                            text: text.to_owned(),
+                            created_at: None,
+                            subtasks: vec![],
+                            tags: vec![],
+                        });
+                    }
+                }
+            }
+            todos
+        }
+    })
+}
+
+/// liest einen Eintrag einer Markdown Checkliste (- [ ] text oder - [x] text)
+fn parse_checklist_item(line: &str) -> Option<(bool, &str)> {
+    let item = line
+        .strip_prefix("- [")
+        .or_else(|| line.strip_prefix("* ["))?;
+    let (mark, text) = item.split_once("] ")?;
+    match mark {
+        " " => Some((false, text.trim())),
+        "x" | "X" => Some((true, text.trim())),
+        _ => None,
+    }
+}
+
+/// Channel und Nutzer werden beim Import und Export gleich geschrieben, wie Twitch die logins schreibt
+pub fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// prüft die importierten Einträge und macht daraus todos
+/// die ids werden erst beim Einfügen vergeben, bis dahin sind sie 0
+pub fn validate_todos(
+    entries: Vec<TransferTodo>,
+    channel: Option<&String>,
+    user: Option<&String>,
+) -> Result<Vec<Todo>, String> {
+    let mut todos = vec![];
+    for (index, entry) in entries.into_iter().enumerate() {
+        let pick = |value: String, default: Option<&String>| {
+            let value = normalize_name(&value);
+            match (value.is_empty(), default) {
+                (true, Some(default)) => normalize_name(default),
+                _ => value,
+            }
+        };
+        let channel = pick(entry.channel, channel);
+        let user = pick(entry.user, user);
+        let (text, mut tags) = parse_tags(entry.text.trim());
+        for tag in entry.tags {
+            let tag = tag.trim_start_matches('#').to_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        if channel.is_empty() || user.is_empty() || text.is_empty() {
+            return Err(format!(
+                "entry {} needs a channel, a user and a text",
+                index + 1
+            ));
+        }
+        // die Liste des Streamers ist kein Nutzer und wird nur über !streamtodo gefüllt
+        if user == STREAM_TODOS {
+            return Err(format!(
+                "entry {}: \"{STREAM_TODOS}\" is not a valid user",
+                index + 1
+            ));
+        }
+        let display_name = match entry.display_name.trim() {
+            "" => user.clone(),
+            display_name => display_name.to_owned(),
+        };
+        todos.push(Todo {
+            id: 0,
+            text,
+            created_at: entry.created_at.unwrap_or_else(Utc::now),
+            channel,
+            user,
+            display_name,
+            tags,
+            subtasks: entry
+                .subtasks
+                .into_iter()
+                .filter(|subtask| !subtask.text.trim().is_empty())
+                .collect(),
+            ..Default::default()
+        });
+    }
+    Ok(todos)
+}
@@ -3,8 +3,92 @@ pub const NO_TODOS_ADDEDD_YET: &str =
 pub const YOUR_TODOS: &str = "Du hast folgende todos:";
 pub const TASK_INDEX_DOESNT_EXIST: &str = "Die Tasknummer existiert nicht :( (Bitte beachte, dass beim Löschen die Nummern weiter rutschen)";
 pub const FINISHED_TODO: (&str, &str) = ("hat", "geschafft :D");
+pub const TOO_MANY_USER_TODOS: (&str, &str) =
+    ("Du hast schon", "offene todos, hake bitte erst welche ab.");
+pub const TODO_TEXT_TOO_LONG: (&str, &str) =
+    ("Dein todo ist zu lang, erlaubt sind höchstens", "Zeichen.");
+pub const TOO_MANY_CHANNEL_TODOS: (&str, &str) = (
+    "Dieser Channel hat schon",
+    "offene todos, bitte warte, bis welche erledigt sind.",
+);
+pub const EDITED_TODO: &str = "hat das todo geändert in:";
+pub const EDIT_USAGE: &str = "Benutzung: !todoedit <nummer> <neuer Text>";
+pub const PRIO_USAGE: &str = "Benutzung: !todoprio <nummer> high|normal|low";
+pub const MOVE_USAGE: &str = "Benutzung: !todomove <nummer> <neue Position>";
+pub const HIGH_PRIORITY_MARK: &str = "[wichtig] ";
+pub const LOW_PRIORITY_MARK: &str = "[später] ";
+pub const ADDED_SUBTASK: &str = "hat den Unterpunkt hinzugefügt:";
+pub const SUBTASK_USAGE: &str = "Benutzung: !todosub <nummer> <Text>";
+pub const NO_TODOS_WITH_TAG: &str = "Du hast keine todos mit diesem Tag.";
+pub const TAG_USAGE: &str = "Tags:";
+pub const TAG_COUNTS: (&str, &str) = ("offen", "erledigt");
+pub const NO_TAGS_YET: &str =
+    "Hier wurden noch keine Tags benutzt, so gehts: !todo Kapitel 3 lesen #lernen";
+pub const REMINDER: &str = "Erinnerung, dein todo ist fällig:";
+pub const REMINDER_SET: &str = "Alles klar, ich erinnere dich um";
+pub const OVERDUE_MARK: &str = "[überfällig] ";
+pub const DUE_MARK: &str = "fällig";
+pub const FOCUS_STARTED: (&str, &str) = ("Fokus-Session gestartet für", "Minuten");
+pub const FOCUS_ENDED: &str =
+    "deine Fokus-Session ist vorbei, gut gemacht! Zeit für eine kurze Pause.";
+pub const FOCUS_STOPPED: &str = "Fokus-Session gestoppt.";
+pub const NO_FOCUS_RUNNING: &str = "Du hast keine laufende Fokus-Session.";
+pub const FOCUS_USAGE: &str = "Benutzung: !focus [minuten] [todo nummer] oder !focus stop";
+pub const POMODORO_WORK: &str = "Pomodoro: Fokuszeit! Die nächste Pause kommt in";
+pub const POMODORO_BREAK: &str = "Pomodoro: Pause! Weiter geht es in";
+pub const POMODORO_STOPPED: &str = "Pomodoro gestoppt.";
+pub const NO_POMODORO_RUNNING: &str = "Es läuft gerade kein Pomodoro.";
+pub const POMODORO_USAGE: &str =
+    "Benutzung: !pomodoro start [fokus minuten] [pausen minuten] oder !pomodoro stop";
+pub const STARTED_TODO: &str = "arbeitet jetzt an:";
+pub const START_USAGE: &str = "Benutzung: !todostart <nummer>";
+pub const IN_PROGRESS_MARK: &str = "[in Arbeit] ";
+pub const TIME_SPENT: &str = "Zeit:";
+pub const CHECK_USAGE: &str =
+    "Benutzung: !check [nummer], z.B. !check 2, !check 1 3 5, !check 2-4, !check all oder !check \"text\"";
+pub const NO_MATCHING_TODO: &str = "Keines deiner todos passt zu dem Text.";
+pub const SEVERAL_MATCHING_TODOS: &str = "Mehrere deiner todos passen, bitte nimm die Nummer:";
+pub const REMOVED_USER_TODO: &str = "hat das todo entfernt von";
+pub const CLEARED_USER_TODOS: &str = "hat alle todos entfernt von";
+pub const ADDED_USER_TODO: &str = "hat ein todo eingetragen für";
+pub const USER_HAS_NO_TODOS: &str = "Dieser Nutzer hat hier keine offenen todos.";
+pub const MOD_REMOVE_USAGE: &str = "Benutzung: !todoremove @nutzer <nummer>";
+pub const MOD_CLEAR_USAGE: &str = "Benutzung: !todoclear @nutzer";
+pub const MOD_ADD_USAGE: &str = "Benutzung: !todoadd @nutzer <text>";
+pub const STREAM_TODOS_HEADER: &str = "Ziele für den Stream:";
+pub const NO_STREAM_TODOS: &str = "Es gibt noch keine Ziele für den Stream.";
+pub const ADDED_STREAM_TODO: &str = "hat ein Ziel für den Stream eingetragen:";
+pub const REMOVED_STREAM_TODO: &str = "hat ein Ziel für den Stream entfernt:";
+pub const STREAM_TODO_USAGE: &str =
+    "Benutzung: !streamtodo <text>, !streamtodo check <nummer> oder !streamtodo remove <nummer>";
+pub const ARCHIVED_TODOS: &str = "Der Stream ist vorbei, alle todos wurden archiviert.";
+pub const NOTHING_TO_ARCHIVE: &str = "Es gibt keine todos zum Archivieren.";
+pub const STREAM_ENDED: &str = "Der Stream ist vorbei, danke fürs Mitmachen! Die Bestenliste für den Stream fängt beim nächsten Mal von vorne an.";
+pub const ERROR_WHEN_ARCHIVING: &str = "Fehler beim Archivieren der todos, bitte schau in die logs";
+pub const STATS_COUNTS: (&str, &str, &str) = ("erstellt", "erledigt", "offen");
+pub const STATS_AVERAGE: &str = "im Schnitt erledigt nach";
+pub const NO_STATS_YET: &str = "Von diesem Nutzer gibt es hier noch keine todos.";
+pub const LEADERBOARD_PERIODS: (&str, &str, &str) =
+    ("In diesem Stream:", "Diese Woche:", "Insgesamt:");
+pub const NOBODY_YET: &str = "noch niemand";
 pub const FLUSHED_TODOS: &str = "todos resettet!";
+pub const CONFIRM_FLUSH: (&str, &str) = (
+    "Das löscht",
+    "todos. Schreibe innerhalb einer Minute !todoflush confirm, um fortzufahren.",
+);
+pub const NO_FLUSH_TO_CONFIRM: &str =
+    "Es gibt keinen Reset zu bestätigen, starte zuerst mit !todoflush.";
+pub const NOTHING_TO_FLUSH: &str = "Es gibt keine todos zum Resetten.";
+pub const FLUSH_USAGE: &str =
+    "Benutzung: !todoflush [@nutzer] [älter als, z.B. 30m, 2h oder 7d] [all], danach !todoflush confirm";
 pub const SAVED_DATA: &str = "Todos gespeichert!";
-pub const ERROR_WHEN_SAVING_DATA: &str = "Fehler beim Speichern der Daten, bitte schaue in die logs";
+pub const ERROR_WHEN_SAVING_DATA: &str =
+    "Fehler beim Speichern der Daten, bitte schaue in die logs";
 pub const NO_PERMISSION: &str = "das darfst du leider nicht machen!";
+pub const RECENTLY_DONE: &str = "Zuletzt erledigt:";
+pub const NOTHING_DONE_YET: &str = "Hier wurden noch keine todos abgehakt.";
+pub const RESTORED_TODO: &str = "hat das todo zurückgeholt:";
+pub const RESTORED_TODOS: &str = "zurückgeholte todos:";
+pub const NOTHING_TO_UNDO: &str =
+    "Es gibt nichts rückgängig zu machen (das geht nur ein paar Minuten nach dem Abhaken oder Resetten).";
 pub const HELP_REPLY: &str = include_str!("./help_reply_de");
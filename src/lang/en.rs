@@ -3,8 +3,91 @@ pub const NO_TODOS_ADDEDD_YET: &str =
 pub const YOUR_TODOS: &str = "You have the following todos:";
 pub const TASK_INDEX_DOESNT_EXIST: &str = "The task number does not exist :( (Please note that the numbers continue to slide when deleted)";
 pub const FINISHED_TODO: (&str, &str) = ("has done", " :D");
+pub const TOO_MANY_USER_TODOS: (&str, &str) = (
+    "You already have",
+    "open todos, please check some off first.",
+);
+pub const TODO_TEXT_TOO_LONG: (&str, &str) =
+    ("Your todo is too long, at most", "characters are allowed.");
+pub const TOO_MANY_CHANNEL_TODOS: (&str, &str) = (
+    "This channel already has",
+    "open todos, please wait until some are done.",
+);
+pub const EDITED_TODO: &str = "changed the todo to:";
+pub const EDIT_USAGE: &str = "Usage: !todoedit <number> <new text>";
+pub const PRIO_USAGE: &str = "Usage: !todoprio <number> high|normal|low";
+pub const MOVE_USAGE: &str = "Usage: !todomove <number> <new position>";
+pub const HIGH_PRIORITY_MARK: &str = "[high] ";
+pub const LOW_PRIORITY_MARK: &str = "[low] ";
+pub const ADDED_SUBTASK: &str = "added the subtask:";
+pub const SUBTASK_USAGE: &str = "Usage: !todosub <number> <text>";
+pub const NO_TODOS_WITH_TAG: &str = "You have no todos with this tag.";
+pub const TAG_USAGE: &str = "Tags:";
+pub const TAG_COUNTS: (&str, &str) = ("open", "done");
+pub const NO_TAGS_YET: &str =
+    "No tags have been used here yet, add them like this: !todo read chapter 3 #study";
+pub const REMINDER: &str = "reminder, your todo is due:";
+pub const REMINDER_SET: &str = "Got it, I will remind you at";
+pub const OVERDUE_MARK: &str = "[overdue] ";
+pub const DUE_MARK: &str = "due";
+pub const FOCUS_STARTED: (&str, &str) = ("Focus session started for", "minutes");
+pub const FOCUS_ENDED: &str = "your focus session is over, well done! Time for a short break.";
+pub const FOCUS_STOPPED: &str = "Focus session stopped.";
+pub const NO_FOCUS_RUNNING: &str = "You have no focus session running.";
+pub const FOCUS_USAGE: &str = "Usage: !focus [minutes] [todo number] or !focus stop";
+pub const POMODORO_WORK: &str = "Pomodoro: focus time! Next break in";
+pub const POMODORO_BREAK: &str = "Pomodoro: break time! Back to work in";
+pub const POMODORO_STOPPED: &str = "Pomodoro stopped.";
+pub const NO_POMODORO_RUNNING: &str = "There is no pomodoro running.";
+pub const POMODORO_USAGE: &str =
+    "Usage: !pomodoro start [focus minutes] [break minutes] or !pomodoro stop";
+pub const STARTED_TODO: &str = "is now working on:";
+pub const START_USAGE: &str = "Usage: !todostart <number>";
+pub const IN_PROGRESS_MARK: &str = "[in progress] ";
+pub const TIME_SPENT: &str = "time spent:";
+pub const CHECK_USAGE: &str =
+    "Usage: !check [number], e.g. !check 2, !check 1 3 5, !check 2-4, !check all or !check \"text\"";
+pub const NO_MATCHING_TODO: &str = "None of your todos matches that text.";
+pub const SEVERAL_MATCHING_TODOS: &str = "Several of your todos match, please use the number:";
+pub const REMOVED_USER_TODO: &str = "removed the todo of";
+pub const CLEARED_USER_TODOS: &str = "removed all todos of";
+pub const ADDED_USER_TODO: &str = "added a todo for";
+pub const USER_HAS_NO_TODOS: &str = "This user has no open todos here.";
+pub const MOD_REMOVE_USAGE: &str = "Usage: !todoremove @user <number>";
+pub const MOD_CLEAR_USAGE: &str = "Usage: !todoclear @user";
+pub const MOD_ADD_USAGE: &str = "Usage: !todoadd @user <text>";
+pub const STREAM_TODOS_HEADER: &str = "Stream goals:";
+pub const NO_STREAM_TODOS: &str = "There are no stream goals yet.";
+pub const ADDED_STREAM_TODO: &str = "added the stream goal:";
+pub const REMOVED_STREAM_TODO: &str = "removed the stream goal:";
+pub const STREAM_TODO_USAGE: &str =
+    "Usage: !streamtodo <text>, !streamtodo check <number> or !streamtodo remove <number>";
+pub const ARCHIVED_TODOS: &str = "The stream is over, all todos were archived.";
+pub const NOTHING_TO_ARCHIVE: &str = "There are no todos to archive.";
+pub const STREAM_ENDED: &str =
+    "The stream is over, thanks for joining! The stream leaderboard starts fresh next time.";
+pub const ERROR_WHEN_ARCHIVING: &str = "error when archiving todos, please look into logs";
+pub const STATS_COUNTS: (&str, &str, &str) = ("added", "done", "open");
+pub const STATS_AVERAGE: &str = "on average done after";
+pub const NO_STATS_YET: &str = "There are no todos from this user here yet.";
+pub const LEADERBOARD_PERIODS: (&str, &str, &str) = ("This stream:", "This week:", "All time:");
+pub const NOBODY_YET: &str = "nobody yet";
 pub const FLUSHED_TODOS: &str = "flushed todos!";
+pub const CONFIRM_FLUSH: (&str, &str) = (
+    "This deletes",
+    "todos. Type !todoflush confirm within a minute to continue.",
+);
+pub const NO_FLUSH_TO_CONFIRM: &str = "There is no flush to confirm, start with !todoflush first.";
+pub const NOTHING_TO_FLUSH: &str = "There are no todos to flush.";
+pub const FLUSH_USAGE: &str =
+    "Usage: !todoflush [@user] [older than, e.g. 30m, 2h or 7d] [all], then !todoflush confirm";
 pub const SAVED_DATA: &str = "saved data!";
 pub const ERROR_WHEN_SAVING_DATA: &str = "error when saving data, please look into logs";
 pub const NO_PERMISSION: &str = "you are not allowed to do that!";
+pub const RECENTLY_DONE: &str = "Recently done:";
+pub const NOTHING_DONE_YET: &str = "No todos have been checked off here yet.";
+pub const RESTORED_TODO: &str = "restored the todo:";
+pub const RESTORED_TODOS: &str = "restored todos:";
+pub const NOTHING_TO_UNDO: &str =
+    "There is nothing to undo (this only works for a few minutes after checking off or flushing).";
 pub const HELP_REPLY: &str = include_str!("./help_reply_en");
@@ -0,0 +1,107 @@
+//! Bestenliste der Nutzer mit den meisten abgehakten todos (!todotop, /leaderboard)
+
+use std::{collections::HashMap, io::ErrorKind};
+
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{file_names::STREAMS, todo::Todo};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub channel: String,
+    pub user: String,
+    pub display_name: String,
+    pub completed: usize,
+}
+
+/// die Bestenlisten für den aktuellen Stream, die aktuelle Woche und insgesamt
+#[derive(Clone, Debug, Serialize)]
+pub struct Leaderboard {
+    pub stream: Vec<LeaderboardEntry>,
+    pub week: Vec<LeaderboardEntry>,
+    pub all_time: Vec<LeaderboardEntry>,
+}
+
+/// wann die Streams der Channels begonnen haben, liegt in der streams.json,
+/// damit ein Neustart des Bots (auch nach einem Absturz) die Bestenliste des Streams nicht zurücksetzt
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamTimes {
+    /// der erste Start des Bots, bis zum ersten !streamend gilt das als Beginn des Streams
+    started_at: DateTime<Utc>,
+    /// wann in einem Channel zuletzt !streamend geschrieben wurde
+    #[serde(default)]
+    stream_ends: HashMap<String, DateTime<Utc>>,
+}
+
+impl StreamTimes {
+    /// liest die streams.json, gibt es sie noch nicht, wird sie mit dem jetzigen Zeitpunkt angelegt
+    pub fn load() -> anyhow::Result<Self> {
+        match std::fs::read_to_string(STREAMS) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let times = Self {
+                    started_at: Utc::now(),
+                    stream_ends: HashMap::new(),
+                };
+                times.save()?;
+                Ok(times)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        std::fs::write(STREAMS, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Beginn des aktuellen Streams: das letzte !streamend im Channel oder der erste Start des Bots
+    pub fn stream_start(&self, channel: Option<&str>) -> DateTime<Utc> {
+        channel
+            .and_then(|channel| self.stream_ends.get(channel))
+            .copied()
+            .unwrap_or(self.started_at)
+    }
+
+    pub fn end_stream(&mut self, channel: &str) -> anyhow::Result<()> {
+        self.stream_ends.insert(channel.to_owned(), Utc::now());
+        self.save()
+    }
+}
+
+/// zählt die abgehakten todos pro Nutzer, die meisten zuerst, höchstens limit Einträge
+/// history darf nur abgehakte todos enthalten, die Liste des Streamers zählt nicht mit
+pub fn top_completers(history: &[Todo], limit: usize) -> Vec<LeaderboardEntry> {
+    let mut counts: HashMap<(&String, &String), LeaderboardEntry> = HashMap::new();
+    for todo in history.iter().filter(|todo| !todo.is_stream_todo()) {
+        counts
+            .entry((&todo.channel, &todo.user))
+            .or_insert_with(|| LeaderboardEntry {
+                channel: todo.channel.clone(),
+                user: todo.user.clone(),
+                display_name: todo.display_name.clone(),
+                completed: 0,
+            })
+            .completed += 1;
+    }
+    let mut top: Vec<LeaderboardEntry> = counts.into_values().collect();
+    top.sort_by(|a, b| {
+        b.completed
+            .cmp(&a.completed)
+            .then_with(|| a.user.cmp(&b.user))
+    });
+    top.truncate(limit);
+    top
+}
+
+/// Montag 0 Uhr (lokale Zeit) der aktuellen Woche
+pub fn week_start() -> DateTime<Utc> {
+    let today = Local::now().date_naive();
+    let monday = today - TimeDelta::days(i64::from(today.weekday().num_days_from_monday()));
+    monday
+        .and_time(NaiveTime::MIN)
+        .and_local_timezone(Local)
+        .earliest()
+        .map_or_else(Utc::now, |start| start.to_utc())
+}
@@ -1,13 +1,18 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use autosave::spawn_autosave_worker;
 use channel_joiner::ChannelJoiner;
 use client_sender::{spawn_sender_worker, ClientSender};
-use config::{load_data, save_data, CredentialsFile, ModSet};
+use config::{load_data, save_data, BotConfig, CredentialsFile, ModSet};
 use log::LevelFilter;
 use log4rs::append::file::FileAppender;
 use log4rs::config::{Appender, Root};
 use log4rs::encode::pattern::PatternEncoder;
+use reminder::spawn_reminder_worker;
+use retention::spawn_retention_worker;
+use storage::restore_backup_interactive;
+use timer::{spawn_timer_worker, Timers};
 use tokio::runtime::Builder;
 use tokio::select;
 use tokio::sync::mpsc::{Receiver, Sender};
@@ -18,21 +23,31 @@ use twitch_irc::TwitchIRCClient;
 use twitch_irc::{ClientConfig, SecureTCPTransport};
 use web::spawn_axum_worker;
 
+mod autosave;
 mod bot;
 mod channel_joiner;
 mod client_sender;
 mod communication;
 mod config;
 mod file_names;
+mod import_export;
 mod lang;
+mod leaderboard;
+mod migration;
+mod moderation;
+mod reminder;
+mod retention;
+mod stats;
+mod storage;
+mod timer;
+mod todo;
+mod undo;
 mod web;
 
 /// Asynchron programmierter todo bot für Twitch
 /// (Wichtig:) Von dem Asynchronen nicht beeindrucken lassen,
 /// wichtig zum lesen ist nur, dass alle async functions erst ausgeführt werden,
 /// wenn hinter ihnen ein ".await" aufgerufen wird (momentan ist eh nichts glechzeitig)
-///
-
 pub async fn async_main() {
     // initialisieren eines Loggers und anderes "vorgeplänkel" (generiert logs nach /log/logfile)
     let logfile = FileAppender::builder()
@@ -63,6 +78,13 @@ pub async fn async_main() {
 }
 
 pub fn main() {
+    // mit --restore-backup kann vor dem Start ein backup der todos.json ausgewählt werden
+    if std::env::args().any(|arg| arg == "--restore-backup") {
+        if let Err(e) = restore_backup_interactive() {
+            eprintln!("could not restore backup: {e}");
+            return;
+        }
+    }
     let runtime = Builder::new_multi_thread()
         .worker_threads(4)
         .thread_name("TwitchTodo")
@@ -88,16 +110,38 @@ pub async fn loop_cycle() -> anyhow::Result<bool> {
     let (send, recv) = mpsc::channel(30);
     let sender_worker = spawn_sender_worker(ClientSender::new(client.clone(), recv, 3));
 
-    let data = load_data().await?;
+    let bot_config = BotConfig::load().await;
+
+    let data = load_data(&bot_config).await?;
+
+    let autosave_worker = spawn_autosave_worker(
+        data.clone(),
+        Duration::from_secs(bot_config.autosave_interval_secs),
+    );
 
     let todo_update_subscriber = Arc::new(Mutex::new(vec![]));
 
+    let reminder_worker =
+        spawn_reminder_worker(data.clone(), send.clone(), todo_update_subscriber.clone());
+
+    let retention_worker = spawn_retention_worker(
+        data.clone(),
+        bot_config.clone(),
+        todo_update_subscriber.clone(),
+    );
+
+    let timers = Timers::default();
+
+    let timer_worker = spawn_timer_worker(timers.clone(), send.clone());
+
     let bot_worker = bot::create_bot_worker(
         incoming_messages,
         send,
         data.clone(),
         mods.clone(),
         todo_update_subscriber.clone(),
+        timers.clone(),
+        bot_config.clone(),
     );
 
     let channel_joiner = Arc::new(Mutex::new(ChannelJoiner::load(client.clone()).await));
@@ -111,18 +155,32 @@ pub async fn loop_cycle() -> anyhow::Result<bool> {
         mods,
         stop_sender,
         todo_update_subscriber,
+        timers,
         data.clone(),
+        bot_config,
     );
 
     let non_blocking = tokio::spawn(async move {
-        let (web, bot, send) = futures::join!(web_worker, bot_worker, sender_worker);
+        let (web, bot, send, autosave, reminder, retention, timer) = futures::join!(
+            web_worker,
+            bot_worker,
+            sender_worker,
+            autosave_worker,
+            reminder_worker,
+            retention_worker,
+            timer_worker
+        );
         web.unwrap();
         bot.unwrap();
         send.unwrap();
+        autosave.unwrap();
+        reminder.unwrap();
+        retention.unwrap();
+        timer.unwrap();
     });
 
     let blocking_thread = tokio::spawn(async move {
-        let _ = stop_recv.recv().await.unwrap();
+        stop_recv.recv().await.unwrap();
         save_data(&data).await.unwrap();
     });
 
@@ -0,0 +1,329 @@
+//! Versionen der gespeicherten Dateien und die Migrationen zwischen ihnen
+//! jede Migration hebt eine Datei um genau eine Version an, ältere Dateien durchlaufen alle Schritte nacheinander.
+//! Vor der ersten Migration wird die Originaldatei als <datei>.v<version>.bak gesichert.
+
+use std::{collections::HashMap, io::Write};
+
+use serde_json::Value;
+use twitch_irc::message::TwitchUserBasics;
+
+use crate::{file_names::CHANNELS_TO_WATCH, todo::TodoStore};
+
+/// 0: Nutzer -> todos (Text), 1: Channel -> Nutzer -> todos (Text), 2: TodoStore mit ids,
+/// 3: todos mit Priorität, Unterpunkten, Tags, Fälligkeit und Zeiterfassung
+pub const TODOS_VERSION: u64 = 3;
+/// 0: Liste der mods, 1: Objekt mit version und mods
+pub const MODS_VERSION: u64 = 1;
+/// 0: nur Channels, 1: Kopfzeile mit der Version
+pub const CHANNELS_VERSION: u64 = 1;
+
+/// Kopfzeile der channels.csv, Zeilen mit # werden beim Einlesen übersprungen
+pub const CHANNELS_HEADER: &str = "# version: ";
+
+type Migration<T> = fn(T) -> anyhow::Result<T>;
+
+const TODO_MIGRATIONS: [Migration<Value>; TODOS_VERSION as usize] =
+    [todos_add_channels, todos_add_ids, todos_add_fields];
+const MOD_MIGRATIONS: [Migration<Value>; MODS_VERSION as usize] = [mods_add_version];
+const CHANNEL_MIGRATIONS: [Migration<String>; CHANNELS_VERSION as usize] = [channels_add_header];
+
+/// führt alle fehlenden Migrationen aus und schreibt das Ergebnis zurück in die Datei
+fn run_migrations<T>(
+    path: &str,
+    mut data: T,
+    version: u64,
+    migrations: &[Migration<T>],
+    to_file: fn(&T) -> anyhow::Result<String>,
+) -> anyhow::Result<T> {
+    if version as usize >= migrations.len() {
+        return Ok(data);
+    }
+
+    let backup = format!("{path}.v{version}.bak");
+    std::fs::copy(path, &backup)?;
+    log::warn!("backed up {path} to {backup} before migrating");
+
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        data = migration(data)?;
+        log::warn!(
+            "migrated {path} from version {from} to version {}",
+            from + 1
+        );
+    }
+
+    // wie beim Speichern erst in eine temporäre Datei schreiben und dann umbenennen,
+    // damit ein Absturz mitten im Schreiben nicht die einzige Kopie der Daten kaputt macht
+    let temp_path = format!("{path}.tmp");
+    let mut file = std::fs::File::create(&temp_path)?;
+    file.write_all(to_file(&data)?.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(data)
+}
+
+fn json_to_file(value: &Value) -> anyhow::Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+/// Version einer json Datei, ältere Dateien ohne Feld werden an ihrer Form erkannt
+fn json_version(value: &Value, detect: fn(&Value) -> u64) -> u64 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or_else(|| detect(value))
+}
+
+pub fn migrate_todos(path: &str, content: &str) -> anyhow::Result<Value> {
+    // eine leere Datei (z.B. nach einem Absturz beim ersten Speichern) ist ein leerer Stand
+    if content.trim().is_empty() {
+        return Ok(serde_json::to_value(TodoStore::default())?);
+    }
+    let value: Value = serde_json::from_str(content)?;
+    let version = match value.get("version").and_then(Value::as_u64) {
+        Some(version) => version,
+        None => detect_todos_version(&value)?,
+    };
+    run_migrations(path, value, version, &TODO_MIGRATIONS, json_to_file)
+}
+
+/// Dateien vor Version 2 hatten kein version Feld, sie werden an ihrer Form erkannt
+/// passt die Form zu keiner Version, wird abgebrochen, statt die einzige Datei der Nutzer falsch umzuschreiben
+fn detect_todos_version(value: &Value) -> anyhow::Result<u64> {
+    let is_text_list = |value: &Value| {
+        value
+            .as_array()
+            .is_some_and(|todos| todos.iter().all(Value::is_string))
+    };
+    let is_user_map = |value: &Value| {
+        value
+            .as_object()
+            .is_some_and(|users| users.values().all(is_text_list))
+    };
+    let Some(object) = value.as_object() else {
+        anyhow::bail!("unknown todo file format: not an object");
+    };
+    if object.contains_key("next_id") {
+        Ok(2)
+    } else if object.is_empty() || object.values().all(is_user_map) {
+        // ein leeres Objekt ist in beiden alten Formaten leer, als 1 muss es keinem Channel zugeordnet werden
+        Ok(1)
+    } else if object.values().all(is_text_list) {
+        Ok(0)
+    } else {
+        anyhow::bail!("unknown todo file format without version")
+    }
+}
+
+pub fn migrate_mods(path: &str, content: &str) -> anyhow::Result<Vec<String>> {
+    let value: Value = serde_json::from_str(content)?;
+    let version = json_version(&value, |_| 0);
+    let mut value = run_migrations(path, value, version, &MOD_MIGRATIONS, json_to_file)?;
+    Ok(serde_json::from_value(value["mods"].take())?)
+}
+
+pub fn migrate_channels(path: &str, content: String) -> anyhow::Result<String> {
+    let version = content
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix(CHANNELS_HEADER))
+        .and_then(|version| version.trim().parse().ok())
+        .unwrap_or(0);
+    run_migrations(path, content, version, &CHANNEL_MIGRATIONS, |content| {
+        Ok(content.clone())
+    })
+}
+
+/// todos ohne id, wie sie vor Version 2 gespeichert wurden
+type TextTodos = HashMap<String, Vec<String>>;
+
+/// 0 -> 1: alte todos hatten keinen Channel, sie werden dem ersten Channel aus der channels.csv zugeordnet
+/// (damals konnte der Bot sinnvoll nur einen Channel bedienen)
+fn todos_add_channels(value: Value) -> anyhow::Result<Value> {
+    let todos: TextTodos = serde_json::from_value(value)?;
+    let channel = first_channel(&std::fs::read_to_string(CHANNELS_TO_WATCH).unwrap_or_default());
+    log::warn!(
+        "moving {} users from legacy todo file to channel \"{channel}\"",
+        todos.len()
+    );
+    Ok(serde_json::to_value(HashMap::from([(channel, todos)]))?)
+}
+
+/// der erste Channel aus dem Inhalt der channels.csv, ohne Kopfzeile
+fn first_channel(channels: &str) -> String {
+    channels
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|channel| channel.trim().replace(',', ""))
+        .find(|channel| !channel.is_empty())
+        .unwrap_or_default()
+}
+
+/// 1 -> 2: alte todos hatten keine ids, sie bekommen neue
+fn todos_add_ids(value: Value) -> anyhow::Result<Value> {
+    let data: HashMap<String, TextTodos> = serde_json::from_value(value)?;
+    let mut store = TodoStore::default();
+    for (channel, todos) in data {
+        for (user, texts) in todos {
+            let author = TwitchUserBasics {
+                id: String::new(),
+                login: user.clone(),
+                name: user,
+            };
+            for text in texts {
+                store.add(&channel, &author, text, None);
+            }
+        }
+    }
+    log::warn!("assigned ids to {} legacy todos", store.next_id);
+    let mut value = serde_json::to_value(store)?;
+    value["version"] = 2.into();
+    Ok(value)
+}
+
+/// 2 -> 3: todos bekommen die neuen Felder (Priorität, Unterpunkte, Tags, Fälligkeit, Zeiterfassung)
+/// mit ihren Standardwerten, die Datei steht danach vollständig in der neuen Form
+fn todos_add_fields(value: Value) -> anyhow::Result<Value> {
+    let store: TodoStore = serde_json::from_value(value)?;
+    let mut value = serde_json::to_value(store)?;
+    value["version"] = 3.into();
+    Ok(value)
+}
+
+/// 0 -> 1: die Liste der mods bekommt eine Versionsnummer
+fn mods_add_version(value: Value) -> anyhow::Result<Value> {
+    Ok(serde_json::json!({ "version": 1, "mods": value }))
+}
+
+/// 0 -> 1: die channels.csv bekommt eine Kopfzeile mit der Version
+fn channels_add_header(content: String) -> anyhow::Result<String> {
+    Ok(format!("{CHANNELS_HEADER}1\n{content}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// legt eine todos.json mit content in einem eigenen temp Ordner an
+    fn todo_file(name: &str, content: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("twitch_todo_bot-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todos.json");
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn migrate(path: &PathBuf) -> anyhow::Result<(u64, TodoStore)> {
+        let content = std::fs::read_to_string(path).unwrap();
+        let value = migrate_todos(path.to_str().unwrap(), &content)?;
+        let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+        Ok((version, serde_json::from_value(value)?))
+    }
+
+    fn texts(store: &TodoStore, channel: &str, user: &str) -> Vec<String> {
+        store
+            .user_todos(channel, user)
+            .map(|todos| todos.iter().map(|todo| todo.text.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn migrates_v0_into_the_first_channel() {
+        let path = todo_file("v0", r#"{"alice":["eins","zwei"],"bob":["drei"]}"#);
+        let (version, store) = migrate(&path).unwrap();
+
+        assert_eq!(version, TODOS_VERSION);
+        assert_eq!(store.channels.len(), 1);
+        let channel = store.channels.keys().next().unwrap().clone();
+        assert_eq!(texts(&store, &channel, "alice"), ["eins", "zwei"]);
+        assert_eq!(texts(&store, &channel, "bob"), ["drei"]);
+        assert_eq!(store.next_id, 3);
+
+        // das Original bleibt als backup, die Datei steht in der neuen Version
+        let backup = path.with_file_name("todos.json.v0.bak");
+        assert_eq!(
+            std::fs::read_to_string(backup).unwrap(),
+            r#"{"alice":["eins","zwei"],"bob":["drei"]}"#
+        );
+        assert_eq!(migrate(&path).unwrap().0, TODOS_VERSION);
+        assert!(!path.with_file_name("todos.json.tmp").exists());
+    }
+
+    #[test]
+    fn migrates_v1_with_channels() {
+        let path = todo_file(
+            "v1",
+            r#"{"vanimio":{"alice":["eins"]},"other":{"bob":["zwei"]}}"#,
+        );
+        let (version, store) = migrate(&path).unwrap();
+
+        assert_eq!(version, TODOS_VERSION);
+        assert_eq!(texts(&store, "vanimio", "alice"), ["eins"]);
+        assert_eq!(texts(&store, "other", "bob"), ["zwei"]);
+        assert!(path.with_file_name("todos.json.v1.bak").exists());
+    }
+
+    #[test]
+    fn migrates_v2_and_keeps_ids() {
+        let path = todo_file(
+            "v2",
+            r#"{"version":2,"next_id":8,"channels":{"vanimio":{"alice":[{"id":7,"text":"eins"}]}},"history":[]}"#,
+        );
+        let (version, store) = migrate(&path).unwrap();
+
+        assert_eq!(version, TODOS_VERSION);
+        assert_eq!(store.next_id, 8);
+        let todo = &store.user_todos("vanimio", "alice").unwrap()[0];
+        assert_eq!((todo.id, todo.text.as_str()), (7, "eins"));
+        assert!(todo.subtasks.is_empty() && todo.tags.is_empty() && todo.due_at.is_none());
+        assert!(path.with_file_name("todos.json.v2.bak").exists());
+    }
+
+    #[test]
+    fn detects_v2_without_version_field() {
+        let path = todo_file("v2-unversioned", r#"{"next_id":0,"channels":{}}"#);
+        assert_eq!(migrate(&path).unwrap().0, TODOS_VERSION);
+        assert!(path.with_file_name("todos.json.v2.bak").exists());
+    }
+
+    #[test]
+    fn leaves_current_version_untouched() {
+        let content = format!(r#"{{"version":{TODOS_VERSION},"next_id":0,"channels":{{}}}}"#);
+        let path = todo_file("current", &content);
+        migrate(&path).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+        assert!(!path
+            .with_file_name(format!("todos.json.v{TODOS_VERSION}.bak"))
+            .exists());
+    }
+
+    #[test]
+    fn empty_file_is_an_empty_store() {
+        for (name, content) in [("empty", ""), ("blank", " \n"), ("empty-object", "{}")] {
+            let path = todo_file(name, content);
+            let (_, store) = migrate(&path).unwrap();
+            assert!(store.channels.is_empty(), "{name}");
+            assert_eq!(store.next_id, 0, "{name}");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_format() {
+        let path = todo_file("unknown", r#"{"alice":1}"#);
+        assert!(migrate(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"alice":1}"#);
+    }
+
+    #[test]
+    fn first_channel_skips_header_and_commas() {
+        assert_eq!(
+            first_channel("# version: 1\n\nvanimio,\nother,\n"),
+            "vanimio"
+        );
+        assert_eq!(first_channel(""), "");
+    }
+}
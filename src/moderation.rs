@@ -0,0 +1,67 @@
+//! Protokoll der Aktionen, mit denen mods die todos anderer Nutzer ändern (!todoremove, !todoclear, !todoadd)
+//! jede Aktion wird als eine json Zeile an die moderation.log angehängt
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::{file_names::MODERATION_LOG, todo::Todo};
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModActionKind {
+    Remove,
+    Clear,
+    Add,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModLogEntry {
+    pub time: DateTime<Utc>,
+    pub channel: String,
+    /// login des mods
+    pub moderator: String,
+    pub action: ModActionKind,
+    /// login des Nutzers, dessen todos geändert wurden
+    pub user: String,
+    pub todos: Vec<Todo>,
+}
+
+impl ModLogEntry {
+    pub fn new(
+        channel: &str,
+        moderator: &str,
+        action: ModActionKind,
+        user: &str,
+        todos: Vec<Todo>,
+    ) -> Self {
+        Self {
+            time: Utc::now(),
+            channel: channel.to_owned(),
+            moderator: moderator.to_owned(),
+            action,
+            user: user.to_owned(),
+            todos,
+        }
+    }
+}
+
+/// hängt die Aktion an das Protokoll an, Fehler werden nur geloggt, damit das Kommando trotzdem durchgeht
+pub async fn log_mod_action(entry: ModLogEntry) {
+    log::warn!("mod action: {:?}", entry);
+    if let Err(e) = append_entry(&entry).await {
+        log::error!("error while writing moderation log: {e}");
+    }
+}
+
+async fn append_entry(entry: &ModLogEntry) -> anyhow::Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(MODERATION_LOG)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
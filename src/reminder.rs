@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use tokio::{sync::mpsc::Sender, task::JoinHandle, time::sleep};
+
+use crate::{
+    bot::Data,
+    communication::{send_todo_update, BotMessage, TodoSubscribers, TodoUpdate},
+    lang::lang,
+};
+
+/// so oft wird nach abgelaufenen todos geschaut
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// erinnert im Chat an todos, deren Zeit abgelaufen ist (!todo 45m ..., !todo at 18:00 ...)
+/// die Zeiten stehen in den gespeicherten todos, nach einem Neustart werden verpasste Erinnerungen nachgeholt
+pub fn spawn_reminder_worker(
+    data: Data,
+    client: Sender<BotMessage>,
+    todo_subscribers: TodoSubscribers,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            sleep(CHECK_INTERVAL).await;
+
+            let due = data.lock().await.due_reminders();
+            for todo in due {
+                log::info!("sending reminder: {:?}", todo);
+                let message = format!("{} {}", lang::REMINDER, todo.text_with_tags());
+                if client
+                    .send(BotMessage {
+                        reciever: Some(todo.user.clone()),
+                        message,
+                        channel: todo.channel.clone(),
+                    })
+                    .await
+                    .is_err()
+                {
+                    log::error!("could not send reminder, the sender is closed");
+                }
+                // das Overlay markiert das todo als überfällig
+                send_todo_update(&todo_subscribers, TodoUpdate::EditTodo(todo)).await;
+            }
+        }
+    })
+}
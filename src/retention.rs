@@ -0,0 +1,111 @@
+//! entfernt offene todos nach den Regeln aus der config.json (retention, channel_retention)
+//! die Liste des Streamers bleibt dabei immer stehen
+
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use chrono::{Local, Utc};
+use tokio::{task::JoinHandle, time::sleep};
+
+use crate::{
+    bot::Data,
+    communication::{send_todo_update, TodoSubscribers, TodoUpdate},
+    config::BotConfig,
+    file_names::TODO_ARCHIVE,
+    import_export::{export_todos, TransferFormat},
+    todo::{FlushScope, Todo},
+};
+
+/// so oft werden die Regeln angewendet
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// löscht regelmäßig die todos, die nach expire_after_days oder daily_reset_at abgelaufen sind
+pub fn spawn_retention_worker(
+    data: Data,
+    config: BotConfig,
+    todo_subscribers: TodoSubscribers,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            sleep(CHECK_INTERVAL).await;
+
+            let now = Local::now();
+            let mut data_locked = data.lock().await;
+            let channels: Vec<String> = data_locked.channels.keys().cloned().collect();
+            let mut expired: HashMap<String, Vec<Todo>> = HashMap::new();
+            for channel in channels {
+                let Some(cutoff) = config.retention_for(&channel).cutoff(now) else {
+                    continue;
+                };
+                let todos = data_locked.expire(&FlushScope {
+                    channel: Some(channel.clone()),
+                    user: None,
+                    older_than: Some(cutoff),
+                });
+                if !todos.is_empty() {
+                    expired.insert(channel, todos);
+                }
+            }
+            drop(data_locked);
+
+            for (channel, todos) in expired {
+                log::warn!("expired {} todos in {channel}: {:?}", todos.len(), todos);
+                let ids = todos.iter().map(|todo| todo.id).collect();
+                send_todo_update(&todo_subscribers, TodoUpdate::FlushTodos { channel, ids }).await;
+            }
+        }
+    })
+}
+
+/// schreibt alle offenen todos des Channels in eine Datei im archive Ordner und leert danach die Listen
+/// gibt die Anzahl der archivierten todos zurück, ist das Schreiben fehlgeschlagen, bleiben die todos stehen
+pub async fn archive_channel(
+    data: &Data,
+    channel: &str,
+    todo_subscribers: &TodoSubscribers,
+) -> anyhow::Result<usize> {
+    let scope = FlushScope {
+        channel: Some(channel.to_owned()),
+        user: None,
+        older_than: None,
+    };
+    let mut data_locked = data.lock().await;
+    let todos: Vec<Todo> = data_locked
+        .channels
+        .get(channel)
+        .into_iter()
+        .flat_map(|todos| todos.values())
+        .flatten()
+        .filter(|todo| scope.matches(todo))
+        .cloned()
+        .collect();
+    if todos.is_empty() {
+        return Ok(0);
+    }
+
+    let format = TransferFormat::Json;
+    let file_name = format!(
+        "{channel}_{}.{}",
+        Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+        format.file_extension()
+    );
+    let path = Path::new(TODO_ARCHIVE).join(file_name);
+    tokio::fs::create_dir_all(TODO_ARCHIVE).await?;
+    tokio::fs::write(&path, export_todos(&todos, format)?).await?;
+
+    let archived = data_locked.expire(&scope);
+    drop(data_locked);
+    log::warn!(
+        "archived {} todos of {channel} to {}",
+        archived.len(),
+        path.display()
+    );
+    send_todo_update(
+        todo_subscribers,
+        TodoUpdate::FlushTodos {
+            channel: channel.to_owned(),
+            ids: archived.iter().map(|todo| todo.id).collect(),
+        },
+    )
+    .await;
+    Ok(archived.len())
+}
@@ -0,0 +1,125 @@
+//! Statistik pro Nutzer und Channel (!todostats, /get_stats)
+//! gerechnet wird mit dem Archiv, damit die Zahlen Neustarts und !todoflush überstehen
+
+use std::collections::HashMap;
+
+use chrono::TimeDelta;
+use serde::Serialize;
+
+use crate::todo::{Todo, TodoStatus};
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct UserStats {
+    pub channel: String,
+    pub user: String,
+    pub display_name: String,
+    /// alle todos, die der Nutzer je erstellt hat (offen, abgehakt und gelöscht)
+    pub added: usize,
+    pub completed: usize,
+    /// Anteil der abgehakten todos an allen erstellten, zwischen 0 und 1
+    pub completion_rate: f64,
+    /// wie lange es im Schnitt vom Erstellen bis zum Abhaken gedauert hat
+    pub average_completion_secs: Option<i64>,
+    pub open: usize,
+    /// die Tags der todos des Nutzers, die meistgenutzten zuerst
+    pub tags: Vec<TagUsage>,
+}
+
+/// wie oft ein Tag bei offenen und erledigten todos vorkommt (!todotags, !todostats)
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TagUsage {
+    pub tag: String,
+    pub open: usize,
+    pub done: usize,
+}
+
+/// zählt die Tags der todos, gelöschte todos zählen nicht mit
+#[derive(Default)]
+pub struct TagCounter {
+    usage: HashMap<String, TagUsage>,
+}
+
+impl TagCounter {
+    pub fn count(&mut self, todo: &Todo) {
+        for tag in todo.tags.iter() {
+            let usage = self.usage.entry(tag.clone()).or_insert_with(|| TagUsage {
+                tag: tag.clone(),
+                ..Default::default()
+            });
+            match todo.status {
+                TodoStatus::Open | TodoStatus::InProgress => usage.open += 1,
+                TodoStatus::Done => usage.done += 1,
+                TodoStatus::Removed => {}
+            }
+        }
+    }
+
+    /// die meistgenutzten Tags zuerst
+    pub fn into_sorted(self) -> Vec<TagUsage> {
+        let mut usage: Vec<TagUsage> = self
+            .usage
+            .into_values()
+            .filter(|usage| usage.open + usage.done > 0)
+            .collect();
+        usage.sort_by(|a, b| {
+            (b.open + b.done)
+                .cmp(&(a.open + a.done))
+                .then_with(|| a.tag.cmp(&b.tag))
+        });
+        usage
+    }
+}
+
+/// rechnet die Statistik aus dem Archiv (abgehakte und gelöschte todos) und den offenen todos
+/// die Liste des Streamers zählt nicht mit, die Nutzer mit den meisten erledigten todos stehen vorne
+pub fn compute_stats<'a>(
+    history: &'a [Todo],
+    open: impl Iterator<Item = &'a Todo>,
+) -> Vec<UserStats> {
+    let mut stats: HashMap<(String, String), (UserStats, TimeDelta, TagCounter)> = HashMap::new();
+    for todo in history.iter().chain(open) {
+        if todo.is_stream_todo() {
+            continue;
+        }
+        let (entry, total_time, tags) = stats
+            .entry((todo.channel.clone(), todo.user.clone()))
+            .or_insert_with(|| {
+                let entry = UserStats {
+                    channel: todo.channel.clone(),
+                    user: todo.user.clone(),
+                    display_name: todo.display_name.clone(),
+                    ..Default::default()
+                };
+                (entry, TimeDelta::zero(), TagCounter::default())
+            });
+        entry.added += 1;
+        tags.count(todo);
+        match todo.status {
+            TodoStatus::Done => {
+                entry.completed += 1;
+                if let Some(completed_at) = todo.completed_at {
+                    *total_time += completed_at - todo.created_at;
+                }
+            }
+            TodoStatus::Open | TodoStatus::InProgress => entry.open += 1,
+            TodoStatus::Removed => {}
+        }
+    }
+
+    let mut stats: Vec<UserStats> = stats
+        .into_values()
+        .map(|(mut entry, total_time, tags)| {
+            entry.tags = tags.into_sorted();
+            entry.completion_rate = entry.completed as f64 / entry.added as f64;
+            entry.average_completion_secs =
+                (entry.completed > 0).then(|| total_time.num_seconds() / entry.completed as i64);
+            entry
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.completed
+            .cmp(&a.completed)
+            .then_with(|| a.user.cmp(&b.user))
+    });
+    stats
+}
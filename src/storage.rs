@@ -0,0 +1,496 @@
+use std::{ops::Deref, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use twitch_irc::message::TwitchUserBasics;
+
+use crate::{
+    config::BotConfig,
+    leaderboard::{top_completers, week_start, Leaderboard, StreamTimes},
+    stats::{compute_stats, TagCounter, TagUsage, UserStats},
+    todo::{parse_tags, FlushScope, HistoryQuery, Todo, TodoPriority, TodoStore},
+    undo::{RemovedTodo, UndoLog},
+};
+
+mod journal;
+mod json;
+mod sqlite;
+
+pub use json::{restore_backup_interactive, JsonStorage};
+pub use sqlite::SqliteStorage;
+
+/// welches Backend die todos speichert, wird in der config.json ausgewählt
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageKind {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+/// Schnittstelle für die Speicherung der todos
+/// die todos liegen immer komplett im Speicher (TodoStore), das Backend bekommt jede Änderung mitgeteilt
+/// und entscheidet selbst, ob es sie sofort schreibt oder erst beim nächsten save
+/// die Methoden schreiben synchron, während der Mutex um TodoData gehalten wird (kein spawn_blocking):
+/// jede Änderung ist nur eine Zeile, und so landen die Änderungen in derselben Reihenfolge
+/// auf der Platte wie im Speicher, auch wenn mehrere Kommandos gleichzeitig kommen
+pub trait TodoStorage: Send {
+    /// lädt alle offenen todos (und je nach Backend das Archiv)
+    fn load(&mut self) -> anyhow::Result<TodoStore>;
+    fn add(&mut self, todo: &Todo) -> anyhow::Result<()>;
+    /// das todo wurde abgehakt und ist jetzt im Archiv
+    fn check(&mut self, todo: &Todo) -> anyhow::Result<()>;
+    fn edit(&mut self, todo: &Todo) -> anyhow::Result<()>;
+    /// die todos wurden gelöscht, ohne abgehakt zu werden, und haben jetzt den Status Removed
+    fn flush(&mut self, todos: &[Todo]) -> anyhow::Result<()>;
+    /// ein abgehaktes oder gelöschtes todo ist wieder offen und steht an der Stelle index
+    fn restore(&mut self, todo: &Todo, index: usize) -> anyhow::Result<()>;
+    /// die Liste eines Nutzers hat eine neue Reihenfolge, todos ist die ganze Liste
+    fn reorder(&mut self, todos: &[Todo]) -> anyhow::Result<()>;
+    /// schreibt den kompletten Stand (wird beim Beenden und bei !savetodos aufgerufen)
+    fn save(&mut self, store: &TodoStore) -> anyhow::Result<()>;
+    fn history(&self, store: &TodoStore, query: &HistoryQuery) -> anyhow::Result<Vec<Todo>>;
+    /// liegt das Archiv nur im Backend? Dann braucht der TodoStore keine Kopie davon
+    fn keeps_history(&self) -> bool {
+        false
+    }
+}
+
+pub fn open_storage(config: &BotConfig) -> anyhow::Result<Box<dyn TodoStorage>> {
+    Ok(match config.storage {
+        StorageKind::Json => Box::new(JsonStorage::new(
+            config.backup_count,
+            Duration::from_secs(config.backup_interval_secs),
+        )),
+        StorageKind::Sqlite => Box::new(SqliteStorage::open()?),
+    })
+}
+
+/// die todos im Speicher zusammen mit ihrem Backend
+/// lesender Zugriff geht über Deref direkt auf den TodoStore, Änderungen nur über die Methoden hier,
+/// damit das Backend nichts verpasst
+pub struct TodoData {
+    store: TodoStore,
+    storage: Box<dyn TodoStorage>,
+    /// gibt es Änderungen, die noch nicht mit save geschrieben wurden?
+    dirty: bool,
+    changes: Arc<Notify>,
+    undo: UndoLog,
+    /// Beginn der Streams für die Bestenliste
+    streams: StreamTimes,
+}
+
+impl Deref for TodoData {
+    type Target = TodoStore;
+
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+impl TodoData {
+    pub fn load(mut storage: Box<dyn TodoStorage>, undo_window: Duration) -> anyhow::Result<Self> {
+        let store = storage.load()?;
+        Ok(Self {
+            store,
+            storage,
+            dirty: false,
+            changes: Arc::new(Notify::new()),
+            undo: UndoLog::new(undo_window),
+            streams: StreamTimes::load()?,
+        })
+    }
+
+    /// wird bei jeder Änderung benachrichtigt (für das automatische Speichern)
+    pub fn changes(&self) -> Arc<Notify> {
+        self.changes.clone()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn mark_changed(&mut self) {
+        // sonst wächst das Archiv im Speicher, solange der Bot läuft, ohne je gelesen zu werden
+        if self.storage.keeps_history() {
+            self.store.history.clear();
+        }
+        self.dirty = true;
+        self.changes.notify_one();
+    }
+
+    pub fn add(
+        &mut self,
+        channel: &str,
+        author: &TwitchUserBasics,
+        text: String,
+        due_at: Option<DateTime<Utc>>,
+    ) -> Todo {
+        let todo = self.store.add(channel, author, text, due_at);
+        log_storage_error(self.storage.add(&todo));
+        // steht das neue todo wegen der Prioritäten nicht am Ende, muss das Backend das wissen
+        if self.is_reordered(&todo) {
+            self.reorder_storage(channel, &todo.user);
+        }
+        self.mark_changed();
+        todo
+    }
+
+    /// ändert die Priorität des todos an der Stelle index, gibt das todo und die neue Reihenfolge zurück
+    pub fn set_priority(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+        priority: TodoPriority,
+    ) -> Option<(Todo, Vec<u64>)> {
+        let todo = self.store.set_priority(channel, user, index, priority)?;
+        log_storage_error(self.storage.edit(&todo));
+        let ids = self.reorder_storage(channel, user);
+        self.mark_changed();
+        Some((todo, ids))
+    }
+
+    /// verschiebt ein todo in der Liste, gibt das todo und die neue Reihenfolge zurück
+    pub fn move_todo(
+        &mut self,
+        channel: &str,
+        user: &str,
+        from: usize,
+        to: usize,
+    ) -> Option<(Todo, Vec<u64>)> {
+        let todo = self.store.move_todo(channel, user, from, to)?;
+        let ids = self.reorder_storage(channel, user);
+        self.mark_changed();
+        Some((todo, ids))
+    }
+
+    /// steht das todo nicht am Ende der Liste seines Nutzers?
+    pub fn is_reordered(&self, todo: &Todo) -> bool {
+        self.store
+            .user_todos(&todo.channel, &todo.user)
+            .and_then(|todos| todos.last())
+            .is_some_and(|last| last.id != todo.id)
+    }
+
+    /// teilt dem Backend die Reihenfolge der Liste mit und gibt die ids in der neuen Reihenfolge zurück
+    fn reorder_storage(&mut self, channel: &str, user: &str) -> Vec<u64> {
+        let todos = self
+            .store
+            .user_todos(channel, user)
+            .cloned()
+            .unwrap_or_default();
+        log_storage_error(self.storage.reorder(&todos));
+        todos.iter().map(|todo| todo.id).collect()
+    }
+
+    pub fn check(&mut self, channel: &str, user: &str, index: usize) -> Option<Todo> {
+        let todo = self.store.check(channel, user, index)?;
+        log_storage_error(self.storage.check(&todo));
+        self.undo.record_check(todo.clone(), index);
+        self.mark_changed();
+        Some(todo)
+    }
+
+    /// setzt das todo an der Stelle index in Arbeit, gibt das gestartete und ein dabei angehaltenes todo zurück
+    pub fn start(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+    ) -> Option<(Todo, Option<Todo>)> {
+        let (started, paused) = self.store.start(channel, user, index)?;
+        log_storage_error(self.storage.edit(&started));
+        if let Some(paused) = paused.as_ref() {
+            log_storage_error(self.storage.edit(paused));
+        }
+        self.mark_changed();
+        Some((started, paused))
+    }
+
+    pub fn add_subtask(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+        text: String,
+    ) -> Option<Todo> {
+        let todo = self.store.add_subtask(channel, user, index, text)?;
+        log_storage_error(self.storage.edit(&todo));
+        self.mark_changed();
+        Some(todo)
+    }
+
+    /// hakt einen Unterpunkt ab, sind danach alle erledigt, wird auch das todo abgehakt
+    /// gibt das todo und die Angabe zurück, ob es jetzt erledigt ist
+    pub fn check_subtask(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+        subtask: usize,
+    ) -> Option<(Todo, bool)> {
+        let todo = self.store.check_subtask(channel, user, index, subtask)?;
+        if todo.subtasks_done() {
+            return self.check(channel, user, index).map(|todo| (todo, true));
+        }
+        log_storage_error(self.storage.edit(&todo));
+        self.mark_changed();
+        Some((todo, false))
+    }
+
+    /// alle offenen todos, deren Zeit abgelaufen ist und an die noch nicht erinnert wurde
+    /// sie werden als erinnert markiert, damit jede Erinnerung nur einmal verschickt wird
+    pub fn due_reminders(&mut self) -> Vec<Todo> {
+        let due: Vec<Todo> = self
+            .store
+            .channels
+            .values_mut()
+            .flat_map(|todos| todos.values_mut())
+            .flatten()
+            .filter(|todo| !todo.reminded && todo.is_overdue())
+            .map(|todo| {
+                todo.reminded = true;
+                todo.clone()
+            })
+            .collect();
+        for todo in due.iter() {
+            log_storage_error(self.storage.edit(todo));
+        }
+        if !due.is_empty() {
+            self.mark_changed();
+        }
+        due
+    }
+
+    /// ändert den Text des todos an der Stelle index, id und Position bleiben gleich
+    pub fn edit(&mut self, channel: &str, user: &str, index: usize, text: String) -> Option<Todo> {
+        let todo = self.store.user_todos_mut(channel, user)?.get_mut(index)?;
+        (todo.text, todo.tags) = parse_tags(&text);
+        let todo = todo.clone();
+        log_storage_error(self.storage.edit(&todo));
+        self.mark_changed();
+        Some(todo)
+    }
+
+    /// holt das zuletzt abgehakte todo des Nutzers zurück, solange das Zeitfenster nicht abgelaufen ist
+    pub fn undo_check(&mut self, channel: &str, user: &str) -> Option<Todo> {
+        let removed = self.undo.take_check(channel, user)?;
+        Some(self.restore(removed))
+    }
+
+    /// holt die todos der letzten Aktion eines mods in dem Channel zurück (z.B. !todoflush)
+    pub fn undo_mod_action(&mut self, channel: &str) -> Option<Vec<Todo>> {
+        let removed = self.undo.take_mod_action(channel)?;
+        Some(
+            removed
+                .into_iter()
+                .map(|removed| self.restore(removed))
+                .collect(),
+        )
+    }
+
+    fn restore(&mut self, removed: RemovedTodo) -> Todo {
+        let todo = self.store.restore(removed.todo, removed.index);
+        log_storage_error(self.storage.restore(&todo, removed.index));
+        self.mark_changed();
+        todo
+    }
+
+    /// fügt importierte todos hinzu, die der Nutzer noch nicht (mit gleichem Text) offen hat
+    /// und die die limits nicht überschreiten
+    /// gibt die tatsächlich hinzugefügten todos mit ihren neuen ids zurück und wie viele über den limits lagen
+    pub fn import(&mut self, todos: Vec<Todo>, config: &BotConfig) -> (Vec<Todo>, usize) {
+        let mut imported = vec![];
+        let mut over_limit = 0;
+        for mut todo in todos {
+            let is_duplicate = self
+                .store
+                .user_todos(&todo.channel, &todo.user)
+                .is_some_and(|existing| {
+                    existing
+                        .iter()
+                        .any(|e| e.text.to_lowercase() == todo.text.to_lowercase())
+                });
+            if is_duplicate {
+                continue;
+            }
+            // ein Import darf nicht mehr, als ein Nutzer mit !todo dürfte
+            let limits = config.limits_for(&todo.channel);
+            if let Err(exceeded) = limits.check(&self.store, &todo.channel, &todo.user, &todo.text)
+            {
+                log::warn!("skipped imported todo {:?}: {exceeded:?}", todo.text);
+                over_limit += 1;
+                continue;
+            }
+            todo.id = self.store.next_id();
+            self.store.insert(todo.clone());
+            log_storage_error(self.storage.add(&todo));
+            imported.push(todo);
+        }
+        if !imported.is_empty() {
+            self.mark_changed();
+        }
+        (imported, over_limit)
+    }
+
+    /// ein mod entfernt das todo an der Stelle index, es kann mit !todoundo mod zurückgeholt werden
+    pub fn remove(&mut self, channel: &str, user: &str, index: usize) -> Option<Todo> {
+        let id = self.store.user_todos(channel, user)?.get(index)?.id;
+        let todo = self.store.discard(id)?;
+        log_storage_error(self.storage.flush(std::slice::from_ref(&todo)));
+        self.undo.record_mod_action(
+            channel,
+            vec![RemovedTodo {
+                todo: todo.clone(),
+                index,
+            }],
+        );
+        self.mark_changed();
+        Some(todo)
+    }
+
+    /// löscht alle offenen todos, auf die der Filter passt
+    /// channel ist der Channel, in dem der mod das Kommando geschickt hat, dort kann es rückgängig gemacht werden
+    pub fn flush(&mut self, scope: &FlushScope, channel: &str) -> Vec<Todo> {
+        let flushed = self.remove_matching(scope);
+        let todos: Vec<Todo> = flushed.iter().map(|r| r.todo.clone()).collect();
+        if !todos.is_empty() {
+            self.undo.record_mod_action(channel, flushed);
+        }
+        todos
+    }
+
+    /// löscht die todos, die nach den Regeln aus der config.json abgelaufen sind
+    /// das kann nicht rückgängig gemacht werden, die todos würden sonst gleich wieder ablaufen
+    pub fn expire(&mut self, scope: &FlushScope) -> Vec<Todo> {
+        self.remove_matching(scope)
+            .into_iter()
+            .map(|removed| removed.todo)
+            .collect()
+    }
+
+    fn remove_matching(&mut self, scope: &FlushScope) -> Vec<RemovedTodo> {
+        let mut flushed = vec![];
+        for todos in self
+            .store
+            .channels
+            .values_mut()
+            .flat_map(|todos| todos.values_mut())
+        {
+            let mut index = 0;
+            todos.retain(|todo| {
+                let keep = !scope.matches(todo);
+                if !keep {
+                    flushed.push(RemovedTodo {
+                        todo: todo.clone(),
+                        index,
+                    });
+                }
+                index += 1;
+                keep
+            });
+        }
+        if flushed.is_empty() {
+            return flushed;
+        }
+        for removed in flushed.iter_mut() {
+            removed.todo.discard();
+            self.store.history.push(removed.todo.clone());
+        }
+        let todos: Vec<Todo> = flushed.iter().map(|r| r.todo.clone()).collect();
+        log_storage_error(self.storage.flush(&todos));
+        self.mark_changed();
+        flushed
+    }
+
+    /// wie oft jeder Tag im Channel benutzt wird: (tag, offen, erledigt), häufigste zuerst
+    pub fn tag_usage(&self, channel: &str) -> Vec<TagUsage> {
+        let mut usage = TagCounter::default();
+        let open = self
+            .store
+            .channels
+            .get(channel)
+            .into_iter()
+            .flat_map(|todos| todos.values())
+            .flatten();
+        for todo in open {
+            usage.count(todo);
+        }
+        let done = self.history(&HistoryQuery {
+            channel: Some(channel.to_owned()),
+            ..Default::default()
+        });
+        for todo in done.iter() {
+            usage.count(todo);
+        }
+        usage.into_sorted()
+    }
+
+    /// Statistik pro Nutzer, mit channel und user nur für diesen Channel bzw. Nutzer
+    pub fn stats(&self, channel: Option<&str>, user: Option<&str>) -> Vec<UserStats> {
+        let history = self.history(&HistoryQuery {
+            channel: channel.map(str::to_owned),
+            user: user.map(str::to_owned),
+            include_removed: true,
+            ..Default::default()
+        });
+        let open = self
+            .store
+            .channels
+            .iter()
+            .filter(|(c, _)| channel.is_none_or(|channel| channel == c.as_str()))
+            .flat_map(|(_, todos)| todos.iter())
+            .filter(|(u, _)| user.is_none_or(|user| user == u.as_str()))
+            .flat_map(|(_, todos)| todos.iter());
+        compute_stats(&history, open)
+    }
+
+    /// der Stream ist vorbei, die Bestenliste für den Stream fängt von vorne an
+    pub fn end_stream(&mut self, channel: &str) {
+        if let Err(e) = self.streams.end_stream(channel) {
+            log::error!("error while saving stream end: {e}");
+        }
+    }
+
+    /// die Nutzer mit den meisten abgehakten todos im Stream, in dieser Woche und insgesamt
+    pub fn leaderboard(&self, channel: Option<&str>, limit: usize) -> Leaderboard {
+        let top = |from: Option<DateTime<Utc>>| {
+            let history = self.history(&HistoryQuery {
+                channel: channel.map(str::to_owned),
+                from,
+                ..Default::default()
+            });
+            top_completers(&history, limit)
+        };
+        Leaderboard {
+            stream: top(Some(self.streams.stream_start(channel))),
+            week: top(Some(week_start())),
+            all_time: top(None),
+        }
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.storage.save(&self.store)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn history(&self, query: &HistoryQuery) -> Vec<Todo> {
+        match self.storage.history(&self.store, query) {
+            Ok(history) => history,
+            Err(e) => {
+                log::error!("error while reading todo history: {e}");
+                vec![]
+            }
+        }
+    }
+}
+
+/// Fehler beim Schreiben sollen den Bot nicht aufhalten, die Änderung ist im Speicher und wird spätestens beim save geschrieben
+fn log_storage_error(result: anyhow::Result<()>) {
+    if let Err(e) = result {
+        log::error!("error while writing todo change: {e}");
+    }
+}
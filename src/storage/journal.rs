@@ -0,0 +1,168 @@
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    file_names::{TODO_AUDIT_LOG, TODO_JOURNAL},
+    todo::{Todo, TodoStore},
+};
+
+/// eine Änderung an den todos, so wie sie im journal steht
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TodoChange {
+    Add {
+        todo: Todo,
+    },
+    Check {
+        todo: Todo,
+    },
+    Edit {
+        todo: Todo,
+    },
+    /// die todos wurden gelöscht und liegen als gelöscht im Archiv
+    Flush {
+        ids: Vec<u64>,
+    },
+    /// ein abgehaktes oder gelöschtes todo wurde mit !todoundo zurückgeholt
+    Restore {
+        todo: Todo,
+        index: usize,
+    },
+    /// neue Reihenfolge der Liste eines Nutzers (!todoprio, !todomove)
+    Reorder {
+        channel: String,
+        user: String,
+        ids: Vec<u64>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    time: DateTime<Utc>,
+    #[serde(flatten)]
+    change: TodoChange,
+}
+
+impl TodoChange {
+    /// wendet die Änderung auf den Stand an
+    /// mehrfaches Anwenden ändert nichts, falls der Snapshot die Änderung schon enthält
+    pub fn apply(self, store: &mut TodoStore) {
+        match self {
+            TodoChange::Add { todo } => {
+                let in_history = store.history.iter().any(|t| t.id == todo.id);
+                if store.find(todo.id).is_none() && !in_history {
+                    store.insert(todo);
+                }
+            }
+            TodoChange::Check { todo } => {
+                store.remove(todo.id);
+                if !store.history.iter().any(|t| t.id == todo.id) {
+                    store.next_id = store.next_id.max(todo.id + 1);
+                    store.history.push(todo);
+                }
+            }
+            TodoChange::Edit { todo } => {
+                if let Some(existing) = store.find_mut(todo.id) {
+                    *existing = todo;
+                }
+            }
+            TodoChange::Flush { ids } => {
+                for id in ids {
+                    store.discard(id);
+                }
+            }
+            TodoChange::Restore { todo, index } => {
+                if store.find(todo.id).is_none() {
+                    store.restore(todo, index);
+                }
+            }
+            TodoChange::Reorder { channel, user, ids } => {
+                store.reorder(&channel, &user, &ids);
+            }
+        }
+    }
+}
+
+/// Append-only Datei, in die jede Änderung sofort geschrieben wird
+/// beim Laden wird sie auf den letzten Snapshot (todos.json) angewendet,
+/// nach jedem Snapshot wird sie in das audit log verschoben und geleert
+pub struct Journal {
+    path: &'static str,
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Self { path: TODO_JOURNAL }
+    }
+
+    pub fn append(&self, change: TodoChange) -> anyhow::Result<()> {
+        let entry = JournalEntry {
+            time: Utc::now(),
+            change,
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path)?;
+        file.write_all(line.as_bytes())?;
+        // bewusst synchron, siehe TodoStorage: der Eintrag ist klein und die Reihenfolge muss stimmen
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// wendet alle Änderungen aus dem journal auf den Stand an
+    pub fn replay(&self, store: &mut TodoStore) -> anyhow::Result<()> {
+        let file = match std::fs::File::open(self.path) {
+            Ok(file) => file,
+            Err(_) => return Ok(()),
+        };
+
+        let mut replayed = 0;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JournalEntry>(&line) {
+                Ok(entry) => {
+                    entry.change.apply(store);
+                    replayed += 1;
+                }
+                // eine halb geschriebene letzte Zeile (Absturz beim Schreiben) wird übersprungen
+                Err(e) => log::error!("skipping unreadable journal entry: {e}"),
+            }
+        }
+        if replayed > 0 {
+            log::warn!("replayed {replayed} changes from {}", self.path);
+        }
+        Ok(())
+    }
+
+    /// nachdem ein Snapshot geschrieben wurde, ist das journal darin enthalten
+    /// die Einträge werden an das audit log angehängt und das journal geleert
+    pub fn compact(&self) -> anyhow::Result<()> {
+        if !Path::new(self.path).exists() {
+            return Ok(());
+        }
+        let entries = std::fs::read(self.path)?;
+        if !entries.is_empty() {
+            let mut audit_log = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(TODO_AUDIT_LOG)?;
+            audit_log.write_all(&entries)?;
+            audit_log.sync_data()?;
+        }
+        std::fs::write(self.path, "")?;
+        Ok(())
+    }
+}
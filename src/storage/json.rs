@@ -0,0 +1,258 @@
+use std::{
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::Local;
+use serde::Serialize;
+
+use crate::{
+    file_names::{TODO_BACKUPS, TODO_SAVE},
+    migration::{migrate_todos, TODOS_VERSION},
+    todo::{HistoryQuery, Todo, TodoStore},
+};
+
+use super::{
+    journal::{Journal, TodoChange},
+    TodoStorage,
+};
+
+/// Inhalt der todos.json: der TodoStore mit einer Versionsnummer davor
+#[derive(Serialize)]
+struct TodoFile<'a> {
+    version: u64,
+    #[serde(flatten)]
+    store: &'a TodoStore,
+}
+
+/// speichert alle todos (inklusive Archiv) als eine json Datei
+/// einzelne Änderungen landen sofort im journal und werden beim nächsten save in die Datei übernommen
+/// vor einem save wird der alte Stand in den backups Ordner kopiert, wenn das letzte backup älter als
+/// backup_interval ist, die letzten backup_count bleiben erhalten
+pub struct JsonStorage {
+    path: &'static str,
+    backup_count: usize,
+    backup_interval: Duration,
+    journal: Journal,
+}
+
+impl JsonStorage {
+    pub fn new(backup_count: usize, backup_interval: Duration) -> Self {
+        Self {
+            path: TODO_SAVE,
+            backup_count,
+            backup_interval,
+            journal: Journal::new(),
+        }
+    }
+
+    /// liest die todos.json (auch für den Umzug nach SQLite), ältere Versionen werden dabei migriert
+    pub fn read(&self) -> anyhow::Result<TodoStore> {
+        let file_string = match std::fs::read_to_string(self.path) {
+            Ok(r) => r,
+            Err(_) => return Ok(TodoStore::default()),
+        };
+
+        let mut data: TodoStore =
+            serde_json::from_value(migrate_todos(self.path, file_string.as_str())?)?;
+        data.fill_missing_metadata();
+        self.journal.replay(&mut data)?;
+        Ok(data)
+    }
+
+    /// kopiert die aktuelle Datei in den backups Ordner und löscht die ältesten backups
+    /// nicht bei jedem autosave, sonst wären nach ein paar Minuten Chat alle älteren Stände weg
+    fn backup(&self) -> anyhow::Result<()> {
+        if self.backup_count == 0 || !Path::new(self.path).exists() {
+            return Ok(());
+        }
+        if let Some(newest) = list_backups()?.first() {
+            let age = std::fs::metadata(newest)?.modified()?.elapsed()?;
+            if age < self.backup_interval {
+                return Ok(());
+            }
+        }
+        std::fs::create_dir_all(TODO_BACKUPS)?;
+        let backup = Path::new(TODO_BACKUPS).join(format!(
+            "todos-{}.json",
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+        std::fs::copy(self.path, backup)?;
+
+        let backups = list_backups()?;
+        for old_backup in backups.iter().skip(self.backup_count) {
+            std::fs::remove_file(old_backup)?;
+        }
+        Ok(())
+    }
+
+    /// ist die todos.json kaputt, wird das neueste lesbare backup wiederhergestellt
+    /// die kaputte Datei wird nicht überschrieben, sondern zur Seite gelegt
+    fn restore_newest_backup(&self) -> anyhow::Result<Option<TodoStore>> {
+        let backups = list_backups().unwrap_or_default();
+        if backups.is_empty() {
+            return Ok(None);
+        }
+        log::warn!(
+            "available backups: {}",
+            backups
+                .iter()
+                .map(|backup| backup.display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+        let broken = move_broken_file(self.path)?;
+        for backup in backups.iter() {
+            std::fs::copy(backup, self.path)?;
+            match self.read() {
+                Ok(store) => {
+                    log::warn!(
+                        "restored {} from {}, the unreadable file was moved to {broken}",
+                        self.path,
+                        backup.display()
+                    );
+                    return Ok(Some(store));
+                }
+                Err(e) => log::error!("backup {} could not be read: {e}", backup.display()),
+            }
+        }
+        // kein backup ist lesbar, die kaputte Datei kommt wieder an ihren Platz
+        std::fs::rename(&broken, self.path)?;
+        Ok(None)
+    }
+}
+
+fn move_broken_file(path: &str) -> anyhow::Result<String> {
+    let broken = format!("{path}.broken-{}", Local::now().format("%Y-%m-%d_%H-%M-%S"));
+    std::fs::rename(path, &broken)?;
+    Ok(broken)
+}
+
+/// fragt in der Konsole, welches backup wiederhergestellt werden soll (Aufruf mit --restore-backup)
+/// läuft vor dem Start der tokio runtime, damit das Warten auf die Eingabe nichts blockiert
+pub fn restore_backup_interactive() -> anyhow::Result<()> {
+    let backups = list_backups()?;
+    if backups.is_empty() {
+        println!("there are no backups in {TODO_BACKUPS}");
+        return Ok(());
+    }
+
+    println!("available backups:");
+    for (index, backup) in backups.iter().enumerate() {
+        println!("  [{}] {}", index + 1, backup.display());
+    }
+    print!("enter a number to restore that backup (or nothing to cancel): ");
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    let backup = match answer.trim().parse::<usize>() {
+        Ok(index) if index > 0 && index <= backups.len() => &backups[index - 1],
+        _ => return Ok(()),
+    };
+
+    let broken = match Path::new(TODO_SAVE).exists() {
+        true => move_broken_file(TODO_SAVE)?,
+        false => String::new(),
+    };
+    std::fs::copy(backup, TODO_SAVE)?;
+    println!("restored {TODO_SAVE} from {}", backup.display());
+    if !broken.is_empty() {
+        println!("the previous file was moved to {broken}");
+    }
+    Ok(())
+}
+
+/// alle backups, neuestes zuerst
+fn list_backups() -> anyhow::Result<Vec<PathBuf>> {
+    let mut backups = vec![];
+    if let Ok(entries) = std::fs::read_dir(TODO_BACKUPS) {
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                backups.push(path);
+            }
+        }
+    }
+    // der Zeitstempel im Dateinamen sortiert sich wie das Datum
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+impl TodoStorage for JsonStorage {
+    fn load(&mut self) -> anyhow::Result<TodoStore> {
+        match self.read() {
+            Ok(store) => Ok(store),
+            Err(e) => {
+                log::error!("error while loading {}: {e}", self.path);
+                match self.restore_newest_backup()? {
+                    Some(store) => Ok(store),
+                    None => Err(e),
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, todo: &Todo) -> anyhow::Result<()> {
+        self.journal.append(TodoChange::Add { todo: todo.clone() })
+    }
+
+    fn check(&mut self, todo: &Todo) -> anyhow::Result<()> {
+        self.journal
+            .append(TodoChange::Check { todo: todo.clone() })
+    }
+
+    fn edit(&mut self, todo: &Todo) -> anyhow::Result<()> {
+        self.journal.append(TodoChange::Edit { todo: todo.clone() })
+    }
+
+    fn flush(&mut self, todos: &[Todo]) -> anyhow::Result<()> {
+        self.journal.append(TodoChange::Flush {
+            ids: todos.iter().map(|todo| todo.id).collect(),
+        })
+    }
+
+    fn restore(&mut self, todo: &Todo, index: usize) -> anyhow::Result<()> {
+        self.journal.append(TodoChange::Restore {
+            todo: todo.clone(),
+            index,
+        })
+    }
+
+    fn reorder(&mut self, todos: &[Todo]) -> anyhow::Result<()> {
+        let Some(first) = todos.first() else {
+            return Ok(());
+        };
+        self.journal.append(TodoChange::Reorder {
+            channel: first.channel.clone(),
+            user: first.user.clone(),
+            ids: todos.iter().map(|todo| todo.id).collect(),
+        })
+    }
+
+    fn save(&mut self, store: &TodoStore) -> anyhow::Result<()> {
+        // leere Listen werden nicht mitgespeichert
+        let file_content = serde_json::to_string(&TodoFile {
+            version: TODOS_VERSION,
+            store: &store.without_empty(),
+        })?;
+        if let Err(e) = self.backup() {
+            log::error!("error while creating todo backup: {e}");
+        }
+        // erst in eine temporäre Datei schreiben und dann umbenennen,
+        // damit bei einem Absturz während des Schreibens die alte Datei erhalten bleibt
+        let temp_path = format!("{}.tmp", self.path);
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(file_content.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, self.path)?;
+        self.journal.compact()?;
+        Ok(())
+    }
+
+    fn history(&self, store: &TodoStore, query: &HistoryQuery) -> anyhow::Result<Vec<Todo>> {
+        Ok(store.history(query))
+    }
+}
@@ -0,0 +1,280 @@
+use std::{path::Path, time::Duration};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    file_names::{TODO_DATABASE, TODO_SAVE},
+    todo::{HistoryQuery, Todo, TodoStatus, TodoStore, STREAM_TODOS},
+};
+
+use super::{JsonStorage, TodoStorage};
+
+/// Version des Datenbankschemas
+const DATABASE_VERSION: i64 = 1;
+
+/// speichert jedes todo als eigene Zeile in einer eingebetteten SQLite Datenbank
+/// jede Änderung wird sofort geschrieben, ein save muss also nicht die ganze Datei neu schreiben
+/// das todo selbst liegt als json in der Spalte `todo`, die anderen Spalten sind nur zum Filtern da
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open() -> anyhow::Result<Self> {
+        let connection = Connection::open(TODO_DATABASE)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS todos (
+                id INTEGER PRIMARY KEY,
+                channel TEXT NOT NULL,
+                user TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                completed_at TEXT,
+                todo TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS todos_history ON todos (status, channel, completed_at);",
+        )?;
+        // die Version des Schemas steht in user_version, spätere Änderungen am Schema können daran anknüpfen
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < DATABASE_VERSION {
+            connection.pragma_update(None, "user_version", DATABASE_VERSION)?;
+            log::warn!("set {TODO_DATABASE} schema version to {DATABASE_VERSION}");
+        }
+        let mut storage = Self { connection };
+
+        // beim ersten Start mit SQLite werden die bisherigen todos aus der todos.json übernommen
+        let is_empty: bool =
+            storage
+                .connection
+                .query_row("SELECT NOT EXISTS (SELECT 1 FROM todos)", [], |row| {
+                    row.get(0)
+                })?;
+        if is_empty && Path::new(TODO_SAVE).exists() {
+            let store = JsonStorage::new(0, Duration::ZERO).read()?;
+            storage.import(&store)?;
+            log::warn!("imported todos from {TODO_SAVE} into {TODO_DATABASE}");
+        }
+
+        Ok(storage)
+    }
+
+    /// schreibt alle offenen und archivierten todos in die Datenbank
+    pub fn import(&mut self, store: &TodoStore) -> anyhow::Result<()> {
+        let transaction = self.connection.transaction()?;
+        for todo in store
+            .channels
+            .values()
+            .flat_map(|todos| todos.values())
+            .flatten()
+            .chain(store.history.iter())
+        {
+            insert_todo(&transaction, todo)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+impl TodoStorage for SqliteStorage {
+    fn load(&mut self) -> anyhow::Result<TodoStore> {
+        let mut store = TodoStore::default();
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT todo FROM todos WHERE status IN (?1, ?2) ORDER BY position, id")?;
+        let rows = statement.query_map(
+            [
+                status_name(TodoStatus::Open),
+                status_name(TodoStatus::InProgress),
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+        for row in rows {
+            let todo: Todo = serde_json::from_str(&row?)?;
+            store
+                .channels
+                .entry(todo.channel.clone())
+                .or_default()
+                .entry(todo.user.clone())
+                .or_default()
+                .push(todo);
+        }
+
+        let max_id: Option<i64> = self
+            .connection
+            .query_row("SELECT MAX(id) FROM todos", [], |row| row.get(0))
+            .optional()?
+            .flatten();
+        store.next_id = max_id.map_or(0, |id| id as u64 + 1);
+
+        Ok(store)
+    }
+
+    fn add(&mut self, todo: &Todo) -> anyhow::Result<()> {
+        insert_todo(&self.connection, todo)
+    }
+
+    fn check(&mut self, todo: &Todo) -> anyhow::Result<()> {
+        self.edit(todo)
+    }
+
+    fn edit(&mut self, todo: &Todo) -> anyhow::Result<()> {
+        self.connection.execute(
+            "UPDATE todos SET status = ?2, completed_at = ?3, todo = ?4 WHERE id = ?1",
+            params![
+                todo.id as i64,
+                status_name(todo.status),
+                todo.completed_at.map(format_time),
+                serde_json::to_string(todo)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn flush(&mut self, todos: &[Todo]) -> anyhow::Result<()> {
+        // gelöschte todos bleiben mit ihrem Status für die Statistik in der Datenbank
+        let transaction = self.connection.transaction()?;
+        for todo in todos {
+            transaction.execute(
+                "UPDATE todos SET status = ?2, completed_at = ?3, todo = ?4 WHERE id = ?1",
+                params![
+                    todo.id as i64,
+                    status_name(todo.status),
+                    todo.completed_at.map(format_time),
+                    serde_json::to_string(todo)?
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn restore(&mut self, todo: &Todo, index: usize) -> anyhow::Result<()> {
+        // die Liste kann seit dem Abhaken umsortiert worden sein (!todomove, !todoprio),
+        // deshalb wird das todo in der Liste aus der Datenbank an die Stelle index gesetzt
+        let transaction = self.connection.transaction()?;
+        insert_todo(&transaction, todo)?;
+        let mut statement = transaction.prepare(
+            "SELECT id FROM todos
+            WHERE channel = ?1 AND user = ?2 AND status IN (?3, ?4) AND id != ?5
+            ORDER BY position, id",
+        )?;
+        let mut ids = statement
+            .query_map(
+                params![
+                    todo.channel,
+                    todo.user,
+                    status_name(TodoStatus::Open),
+                    status_name(TodoStatus::InProgress),
+                    todo.id as i64
+                ],
+                |row| row.get::<_, i64>(0),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(statement);
+        ids.insert(index.min(ids.len()), todo.id as i64);
+        set_order(&transaction, &ids)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn reorder(&mut self, todos: &[Todo]) -> anyhow::Result<()> {
+        let transaction = self.connection.transaction()?;
+        let ids: Vec<i64> = todos.iter().map(|todo| todo.id as i64).collect();
+        set_order(&transaction, &ids)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn save(&mut self, _store: &TodoStore) -> anyhow::Result<()> {
+        // jede Änderung ist schon geschrieben
+        Ok(())
+    }
+
+    fn history(&self, _store: &TodoStore, query: &HistoryQuery) -> anyhow::Result<Vec<Todo>> {
+        let mut statement = self.connection.prepare(
+            "SELECT todo FROM todos
+            WHERE (status = ?1 OR (?7 AND status = ?8))
+                AND (?2 IS NULL OR channel = ?2)
+                AND (?3 IS NULL OR user = ?3)
+                AND (?4 IS NULL OR completed_at >= ?4)
+                AND (?5 IS NULL OR completed_at < ?5)
+                AND user != ?9
+            ORDER BY completed_at DESC
+            LIMIT ?6",
+        )?;
+        let rows = statement.query_map(
+            params![
+                status_name(TodoStatus::Done),
+                query.channel,
+                query.user,
+                query.from.map(format_time),
+                query.to.map(format_time),
+                query.limit.map_or(-1, |limit| limit as i64),
+                query.include_removed,
+                status_name(TodoStatus::Removed),
+                STREAM_TODOS,
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+        let mut history = vec![];
+        for row in rows {
+            history.push(serde_json::from_str(&row?)?);
+        }
+        Ok(history)
+    }
+
+    fn keeps_history(&self) -> bool {
+        true
+    }
+}
+
+fn insert_todo(connection: &Connection, todo: &Todo) -> anyhow::Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO todos (id, channel, user, position, status, completed_at, todo)
+        VALUES (?1, ?2, ?3, ?1, ?4, ?5, ?6)",
+        params![
+            todo.id as i64,
+            todo.channel,
+            todo.user,
+            status_name(todo.status),
+            todo.completed_at.map(format_time),
+            serde_json::to_string(todo)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// die todos bekommen die gleichen Positionen wie vorher, nur in der Reihenfolge von ids,
+/// damit sich an der Reihenfolge zu anderen Nutzern und neuen todos nichts ändert
+fn set_order(connection: &Connection, ids: &[i64]) -> anyhow::Result<()> {
+    let mut positions = vec![];
+    for id in ids {
+        let position: i64 =
+            connection.query_row("SELECT position FROM todos WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })?;
+        positions.push(position);
+    }
+    positions.sort();
+    for (id, position) in ids.iter().zip(positions) {
+        connection.execute(
+            "UPDATE todos SET position = ?2 WHERE id = ?1",
+            params![id, position],
+        )?;
+    }
+    Ok(())
+}
+
+fn status_name(status: TodoStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+/// immer das gleiche Format, damit die Zeitpunkte als Text verglichen werden können
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
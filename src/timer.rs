@@ -0,0 +1,284 @@
+//! Fokus-Timer der Zuschauer (!focus) und der Pomodoro eines Channels (!pomodoro)
+//! die Timer liegen nur im Speicher, nach einem Neustart sind sie weg
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use tokio::{
+    sync::{mpsc::Sender, Mutex},
+    task::JoinHandle,
+    time::sleep,
+};
+
+use twitch_irc::message::TwitchUserBasics;
+
+use crate::{
+    communication::{send_to_subscribers, BotMessage, Subscribers},
+    lang::lang,
+    todo::Todo,
+};
+
+/// so oft wird nach abgelaufenen Timern geschaut
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PomodoroPhase {
+    Work,
+    Break,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimerKind {
+    /// Fokus-Session eines Zuschauers, optional zu einem seiner todos
+    Focus {
+        user: String,
+        display_name: String,
+        todo_id: Option<u64>,
+        todo_text: Option<String>,
+    },
+    /// Pomodoro für den ganzen Channel, Arbeit und Pause wechseln sich ab, bis ein mod ihn stoppt
+    Pomodoro {
+        phase: PomodoroPhase,
+        round: u32,
+        work_minutes: u32,
+        break_minutes: u32,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Timer {
+    pub id: u64,
+    pub channel: String,
+    #[serde(flatten)]
+    pub kind: TimerKind,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+impl Timer {
+    fn is_focus_of(&self, channel: &str, login: &str) -> bool {
+        self.channel == channel
+            && matches!(&self.kind, TimerKind::Focus { user, .. } if user == login)
+    }
+
+    fn is_pomodoro_of(&self, channel: &str) -> bool {
+        self.channel == channel && matches!(self.kind, TimerKind::Pomodoro { .. })
+    }
+}
+
+#[derive(Clone)]
+pub enum TimerUpdate {
+    Started(Timer),
+    Ended(Timer),
+}
+
+impl TimerUpdate {
+    pub fn channel(&self) -> &String {
+        match self {
+            TimerUpdate::Started(timer) | TimerUpdate::Ended(timer) => &timer.channel,
+        }
+    }
+}
+
+/// alle offenen Verbindungen des Timer-Overlays (/timers_sse)
+pub type TimerSubscribers = Subscribers<TimerUpdate>;
+
+#[derive(Default)]
+pub struct TimerStore {
+    next_id: u64,
+    pub timers: Vec<Timer>,
+}
+
+impl TimerStore {
+    fn start(&mut self, channel: &str, kind: TimerKind, minutes: u32) -> Timer {
+        let started_at = Utc::now();
+        let timer = Timer {
+            id: self.next_id,
+            channel: channel.to_owned(),
+            kind,
+            started_at,
+            ends_at: started_at + TimeDelta::minutes(i64::from(minutes)),
+        };
+        self.next_id += 1;
+        self.timers.push(timer.clone());
+        timer
+    }
+
+    /// entfernt alle Timer, auf die filter passt, und gibt sie zurück
+    fn take(&mut self, filter: impl Fn(&Timer) -> bool) -> Vec<Timer> {
+        let (taken, kept) = std::mem::take(&mut self.timers)
+            .into_iter()
+            .partition(filter);
+        self.timers = kept;
+        taken
+    }
+}
+
+/// die Timer zusammen mit den Overlays, die über Änderungen informiert werden
+#[derive(Clone, Default)]
+pub struct Timers {
+    pub store: Arc<Mutex<TimerStore>>,
+    pub subscribers: TimerSubscribers,
+}
+
+impl Timers {
+    /// startet eine Fokus-Session, eine laufende Session des Nutzers wird dabei beendet
+    pub async fn start_focus(
+        &self,
+        channel: &str,
+        author: &TwitchUserBasics,
+        todo: Option<&Todo>,
+        minutes: u32,
+    ) -> Timer {
+        let kind = TimerKind::Focus {
+            user: author.login.clone(),
+            display_name: author.name.clone(),
+            todo_id: todo.map(|todo| todo.id),
+            todo_text: todo.map(|todo| todo.text.clone()),
+        };
+        let (replaced, timer) = {
+            let mut store = self.store.lock().await;
+            let replaced = store.take(|timer| timer.is_focus_of(channel, &author.login));
+            (replaced, store.start(channel, kind, minutes))
+        };
+        self.announce(replaced, Some(timer.clone())).await;
+        timer
+    }
+
+    pub async fn stop_focus(&self, channel: &str, login: &str) -> Option<Timer> {
+        let stopped = self
+            .store
+            .lock()
+            .await
+            .take(|timer| timer.is_focus_of(channel, login));
+        self.announce(stopped.clone(), None).await;
+        stopped.into_iter().next()
+    }
+
+    /// startet einen Pomodoro im Channel mit der ersten Arbeitsphase, ein laufender wird ersetzt
+    pub async fn start_pomodoro(
+        &self,
+        channel: &str,
+        work_minutes: u32,
+        break_minutes: u32,
+    ) -> Timer {
+        let kind = TimerKind::Pomodoro {
+            phase: PomodoroPhase::Work,
+            round: 1,
+            work_minutes,
+            break_minutes,
+        };
+        let (replaced, timer) = {
+            let mut store = self.store.lock().await;
+            let replaced = store.take(|timer| timer.is_pomodoro_of(channel));
+            (replaced, store.start(channel, kind, work_minutes))
+        };
+        self.announce(replaced, Some(timer.clone())).await;
+        timer
+    }
+
+    pub async fn stop_pomodoro(&self, channel: &str) -> Option<Timer> {
+        let stopped = self
+            .store
+            .lock()
+            .await
+            .take(|timer| timer.is_pomodoro_of(channel));
+        self.announce(stopped.clone(), None).await;
+        stopped.into_iter().next()
+    }
+
+    async fn announce(&self, ended: Vec<Timer>, started: Option<Timer>) {
+        for timer in ended {
+            send_to_subscribers(&self.subscribers, TimerUpdate::Ended(timer)).await;
+        }
+        if let Some(timer) = started {
+            send_to_subscribers(&self.subscribers, TimerUpdate::Started(timer)).await;
+        }
+    }
+}
+
+/// beendet abgelaufene Timer, schreibt in den Chat und startet beim Pomodoro die nächste Phase
+pub fn spawn_timer_worker(timers: Timers, client: Sender<BotMessage>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            sleep(CHECK_INTERVAL).await;
+
+            let now = Utc::now();
+            let mut store = timers.store.lock().await;
+            let ended = store.take(|timer| timer.ends_at <= now);
+            let mut started = vec![];
+            let mut messages = vec![];
+            for timer in ended.iter() {
+                match &timer.kind {
+                    TimerKind::Focus {
+                        user, todo_text, ..
+                    } => {
+                        let message = match todo_text {
+                            Some(text) => format!("{} ({text})", lang::FOCUS_ENDED),
+                            None => lang::FOCUS_ENDED.to_owned(),
+                        };
+                        messages.push((Some(user.clone()), timer.channel.clone(), message));
+                    }
+                    TimerKind::Pomodoro {
+                        phase,
+                        round,
+                        work_minutes,
+                        break_minutes,
+                    } => {
+                        let (phase, round, minutes, message) = match phase {
+                            PomodoroPhase::Work => (
+                                PomodoroPhase::Break,
+                                *round,
+                                *break_minutes,
+                                lang::POMODORO_BREAK,
+                            ),
+                            PomodoroPhase::Break => (
+                                PomodoroPhase::Work,
+                                round + 1,
+                                *work_minutes,
+                                lang::POMODORO_WORK,
+                            ),
+                        };
+                        let next = TimerKind::Pomodoro {
+                            phase,
+                            round,
+                            work_minutes: *work_minutes,
+                            break_minutes: *break_minutes,
+                        };
+                        started.push(store.start(&timer.channel, next, minutes));
+                        messages.push((
+                            None,
+                            timer.channel.clone(),
+                            format!("{message} {minutes} min"),
+                        ));
+                    }
+                }
+            }
+            drop(store);
+
+            for timer in ended {
+                log::info!("timer ended: {:?}", timer);
+                send_to_subscribers(&timers.subscribers, TimerUpdate::Ended(timer)).await;
+            }
+            for timer in started {
+                send_to_subscribers(&timers.subscribers, TimerUpdate::Started(timer)).await;
+            }
+            for (reciever, channel, message) in messages {
+                if client
+                    .send(BotMessage {
+                        reciever,
+                        message,
+                        channel,
+                    })
+                    .await
+                    .is_err()
+                {
+                    log::error!("could not send timer message, the sender is closed");
+                }
+            }
+        }
+    })
+}
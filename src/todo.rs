@@ -0,0 +1,621 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use twitch_irc::message::TwitchUserBasics;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    #[default]
+    Open,
+    InProgress,
+    Done,
+    /// gelöscht, ohne abgehakt zu werden (!todoflush, mods, Regeln aus der config.json)
+    /// bleibt für die Statistik im Archiv, taucht aber in keinem Verlauf auf
+    Removed,
+}
+
+/// Wichtigkeit eines todos, neue todos werden danach einsortiert (wichtige zuerst)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl TodoPriority {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "high" => Some(TodoPriority::High),
+            "normal" => Some(TodoPriority::Normal),
+            "low" => Some(TodoPriority::Low),
+            _ => None,
+        }
+    }
+}
+
+/// ein Unterpunkt eines todos (!todosub), wird mit !check <todo>.<unterpunkt> abgehakt
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Subtask {
+    pub text: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// ein einzelnes todo, die id bleibt über Neustarts hinweg gleich (wird in der todos.json gespeichert)
+/// alle Felder außer id und text haben Standardwerte, damit ältere todos.json Dateien lesbar bleiben
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Todo {
+    pub id: u64,
+    pub text: String,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub channel: String,
+    /// login des Nutzers, der das todo erstellt hat
+    #[serde(default)]
+    pub user: String,
+    #[serde(default)]
+    pub user_id: String,
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub status: TodoStatus,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub priority: TodoPriority,
+    #[serde(default)]
+    pub subtasks: Vec<Subtask>,
+    /// Hashtags aus dem Text (ohne #, klein geschrieben)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// bis wann das todo erledigt sein soll (!todo 45m ..., !todo at 18:00 ...)
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    /// wurde schon an das todo erinnert? wird mitgespeichert, damit nach einem Neustart nicht doppelt erinnert wird
+    #[serde(default)]
+    pub reminded: bool,
+    /// seit wann das todo in Arbeit ist (!todostart), None wenn es gerade nicht bearbeitet wird
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    /// bisher mit !todostart an dem todo verbrachte Zeit, ohne die laufende Bearbeitung
+    #[serde(default)]
+    pub time_spent_secs: i64,
+}
+
+/// unter diesem Nutzer liegt die Liste des Streamers (!streamtodo), ein Twitch login kann kein # enthalten
+pub const STREAM_TODOS: &str = "#stream";
+
+/// nimmt die Hashtags (#lernen) aus dem Text und gibt den restlichen Text und die Tags zurück
+/// besteht der Text nur aus Tags, bleibt er unverändert
+pub fn parse_tags(text: &str) -> (String, Vec<String>) {
+    let mut words = vec![];
+    let mut tags: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        let tag = word
+            .strip_prefix('#')
+            .map(|tag| tag.trim_end_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|tag| {
+                !tag.is_empty()
+                    && tag
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            });
+        match tag {
+            Some(tag) => {
+                let tag = tag.to_lowercase();
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            None => words.push(word),
+        }
+    }
+    if words.is_empty() {
+        return (text.to_owned(), tags);
+    }
+    (words.join(" "), tags)
+}
+
+impl Todo {
+    /// gehört das todo zur Liste des Streamers statt zu einem Zuschauer?
+    pub fn is_stream_todo(&self) -> bool {
+        self.user == STREAM_TODOS
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim_start_matches('#').to_lowercase();
+        self.tags.contains(&tag)
+    }
+
+    pub fn is_overdue(&self) -> bool {
+        self.due_at.is_some_and(|due_at| due_at <= Utc::now())
+    }
+
+    /// der Text mit den Tags dahinter, so wie er eingegeben wurde (für den Export)
+    pub fn text_with_tags(&self) -> String {
+        let mut text = self.text.clone();
+        for tag in self.tags.iter() {
+            text.push_str(format!(" #{tag}").as_str());
+        }
+        text
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.status == TodoStatus::InProgress
+    }
+
+    /// markiert das todo als in Arbeit, die Zeit läuft ab jetzt
+    pub fn start(&mut self) {
+        self.status = TodoStatus::InProgress;
+        self.started_at = Some(Utc::now());
+    }
+
+    /// hält die Bearbeitung an und rechnet die Zeit seit dem Start dazu
+    pub fn pause(&mut self) {
+        self.time_spent_secs = self.time_spent().num_seconds();
+        self.started_at = None;
+        if self.is_in_progress() {
+            self.status = TodoStatus::Open;
+        }
+    }
+
+    /// die gesamte Bearbeitungszeit, eine laufende Bearbeitung mitgerechnet
+    pub fn time_spent(&self) -> TimeDelta {
+        let running = self
+            .started_at
+            .map_or(TimeDelta::zero(), |started_at| Utc::now() - started_at);
+        TimeDelta::seconds(self.time_spent_secs) + running.max(TimeDelta::zero())
+    }
+
+    pub fn complete(&mut self) {
+        self.pause();
+        self.status = TodoStatus::Done;
+        self.completed_at = Some(Utc::now());
+    }
+
+    /// erledigte und alle Unterpunkte
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self.subtasks.iter().filter(|subtask| subtask.done).count();
+        (done, self.subtasks.len())
+    }
+
+    /// hat das todo Unterpunkte und sind alle erledigt?
+    pub fn subtasks_done(&self) -> bool {
+        !self.subtasks.is_empty() && self.subtasks.iter().all(|subtask| subtask.done)
+    }
+
+    /// wie complete, nur für todos, die gelöscht statt abgehakt werden, completed_at ist dann der Zeitpunkt des Löschens
+    pub fn discard(&mut self) {
+        self.pause();
+        self.status = TodoStatus::Removed;
+        self.completed_at = Some(Utc::now());
+    }
+
+    pub fn reopen(&mut self) {
+        self.status = TodoStatus::Open;
+        self.completed_at = None;
+    }
+}
+
+/// todos eines Channels, nach Nutzer (login) sortiert
+pub type ChannelTodos = HashMap<String, Vec<Todo>>;
+
+/// alle todos, erst nach Channel und dann nach Nutzer sortiert
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TodoStore {
+    /// die nächste freie id, wird nur hochgezählt, damit keine id doppelt vergeben wird
+    pub next_id: u64,
+    pub channels: HashMap<String, ChannelTodos>,
+    /// abgehakte todos, älteste zuerst
+    #[serde(default)]
+    pub history: Vec<Todo>,
+}
+
+/// Filter für das Archiv der erledigten todos, die Liste des Streamers gehört nie dazu
+#[derive(Clone, Debug, Default)]
+pub struct HistoryQuery {
+    pub channel: Option<String>,
+    pub user: Option<String>,
+    /// nur todos, die ab diesem Zeitpunkt erledigt wurden
+    pub from: Option<DateTime<Utc>>,
+    /// nur todos, die vor diesem Zeitpunkt erledigt wurden
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    /// auch gelöschte todos (für die Statistik), sonst nur abgehakte
+    pub include_removed: bool,
+}
+
+impl HistoryQuery {
+    pub fn matches(&self, todo: &Todo) -> bool {
+        let completed_at = todo.completed_at.unwrap_or(todo.created_at);
+        (todo.status == TodoStatus::Done || self.include_removed)
+            && self.channel.as_ref().is_none_or(|c| c == &todo.channel)
+            && self.user.as_ref().is_none_or(|u| u == &todo.user)
+            && self.from.is_none_or(|from| completed_at >= from)
+            && self.to.is_none_or(|to| completed_at < to)
+            && !todo.is_stream_todo()
+    }
+}
+
+/// welche offenen todos ein !todoflush löscht, None heißt jeweils ohne Einschränkung
+/// die Liste des Streamers wird nie mitgelöscht
+#[derive(Clone, Debug, Default)]
+pub struct FlushScope {
+    pub channel: Option<String>,
+    pub user: Option<String>,
+    /// nur todos, die vor diesem Zeitpunkt erstellt wurden
+    pub older_than: Option<DateTime<Utc>>,
+}
+
+impl FlushScope {
+    pub fn matches(&self, todo: &Todo) -> bool {
+        self.channel.as_ref().is_none_or(|c| c == &todo.channel)
+            && self.user.as_ref().is_none_or(|u| u == &todo.user)
+            && self.older_than.is_none_or(|time| todo.created_at < time)
+            && !todo.is_stream_todo()
+    }
+}
+
+impl TodoStore {
+    pub fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// erstellt ein neues todo mit neuer id und fügt es in die Liste des Nutzers ein
+    pub fn add(
+        &mut self,
+        channel: &str,
+        author: &TwitchUserBasics,
+        text: String,
+        due_at: Option<DateTime<Utc>>,
+    ) -> Todo {
+        let (text, tags) = parse_tags(&text);
+        let todo = Todo {
+            id: self.next_id(),
+            text,
+            tags,
+            due_at,
+            created_at: Utc::now(),
+            channel: channel.to_owned(),
+            user: author.login.clone(),
+            user_id: author.id.clone(),
+            display_name: author.name.clone(),
+            ..Default::default()
+        };
+        self.insert(todo.clone());
+        todo
+    }
+
+    pub fn user_todos(&self, channel: &str, user: &str) -> Option<&Vec<Todo>> {
+        self.channels.get(channel).and_then(|todos| todos.get(user))
+    }
+
+    pub fn user_todos_mut(&mut self, channel: &str, user: &str) -> Option<&mut Vec<Todo>> {
+        self.channels
+            .get_mut(channel)
+            .and_then(|todos| todos.get_mut(user))
+    }
+
+    /// Anzahl der offenen todos der Zuschauer im Channel, die Liste des Streamers zählt nicht mit
+    pub fn channel_count(&self, channel: &str) -> usize {
+        self.channels.get(channel).map_or(0, |todos| {
+            todos
+                .iter()
+                .filter(|(user, _)| user.as_str() != STREAM_TODOS)
+                .map(|(_, todos)| todos.len())
+                .sum()
+        })
+    }
+
+    pub fn find(&self, id: u64) -> Option<&Todo> {
+        self.channels
+            .values()
+            .flat_map(|todos| todos.values())
+            .flatten()
+            .find(|todo| todo.id == id)
+    }
+
+    pub fn find_mut(&mut self, id: u64) -> Option<&mut Todo> {
+        self.channels
+            .values_mut()
+            .flat_map(|todos| todos.values_mut())
+            .flatten()
+            .find(|todo| todo.id == id)
+    }
+
+    /// fügt ein bestehendes todo (mit seiner id) in die Liste seines Nutzers ein,
+    /// hinter dem letzten todo mit gleicher oder höherer Priorität
+    /// die Liste muss dafür nicht sortiert sein, mit !todomove kann ein todo auch vor wichtigere rutschen
+    pub fn insert(&mut self, todo: Todo) {
+        self.next_id = self.next_id.max(todo.id + 1);
+        let todos = self
+            .channels
+            .entry(todo.channel.clone())
+            .or_default()
+            .entry(todo.user.clone())
+            .or_default();
+        let index = todos
+            .iter()
+            .rposition(|t| t.priority <= todo.priority)
+            .map_or(0, |index| index + 1);
+        todos.insert(index, todo);
+    }
+
+    /// ändert die Priorität des todos an der Stelle index und sortiert es neu ein
+    pub fn set_priority(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+        priority: TodoPriority,
+    ) -> Option<Todo> {
+        let todos = self.user_todos_mut(channel, user)?;
+        if index >= todos.len() {
+            return None;
+        }
+        let mut todo = todos.remove(index);
+        todo.priority = priority;
+        self.insert(todo.clone());
+        Some(todo)
+    }
+
+    /// verschiebt das todo von der Stelle from an die Stelle to
+    pub fn move_todo(&mut self, channel: &str, user: &str, from: usize, to: usize) -> Option<Todo> {
+        let todos = self.user_todos_mut(channel, user)?;
+        if from >= todos.len() {
+            return None;
+        }
+        let todo = todos.remove(from);
+        todos.insert(to.min(todos.len()), todo.clone());
+        Some(todo)
+    }
+
+    /// hängt einen Unterpunkt an das todo an der Stelle index an
+    pub fn add_subtask(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+        text: String,
+    ) -> Option<Todo> {
+        let todo = self.user_todos_mut(channel, user)?.get_mut(index)?;
+        todo.subtasks.push(Subtask { text, done: false });
+        Some(todo.clone())
+    }
+
+    /// hakt den Unterpunkt subtask des todos an der Stelle index ab
+    pub fn check_subtask(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+        subtask: usize,
+    ) -> Option<Todo> {
+        let todo = self.user_todos_mut(channel, user)?.get_mut(index)?;
+        todo.subtasks.get_mut(subtask)?.done = true;
+        Some(todo.clone())
+    }
+
+    /// setzt das todo an der Stelle index in Arbeit und hält ein anderes todo des Nutzers an, das gerade in Arbeit ist
+    /// gibt das gestartete und das angehaltene todo zurück
+    pub fn start(
+        &mut self,
+        channel: &str,
+        user: &str,
+        index: usize,
+    ) -> Option<(Todo, Option<Todo>)> {
+        let todos = self.user_todos_mut(channel, user)?;
+        if index >= todos.len() {
+            return None;
+        }
+        if todos[index].is_in_progress() {
+            return Some((todos[index].clone(), None));
+        }
+        let paused = todos
+            .iter_mut()
+            .find(|todo| todo.is_in_progress())
+            .map(|todo| {
+                todo.pause();
+                todo.clone()
+            });
+        todos[index].start();
+        Some((todos[index].clone(), paused))
+    }
+
+    /// bringt die Liste des Nutzers in die Reihenfolge der ids, unbekannte todos bleiben hinten
+    pub fn reorder(&mut self, channel: &str, user: &str, ids: &[u64]) {
+        if let Some(todos) = self.user_todos_mut(channel, user) {
+            todos.sort_by_key(|todo| {
+                ids.iter()
+                    .position(|id| *id == todo.id)
+                    .unwrap_or(ids.len())
+            });
+        }
+    }
+
+    /// entfernt ein offenes todo, ohne es abzuhaken
+    pub fn remove(&mut self, id: u64) -> Option<Todo> {
+        for todos in self
+            .channels
+            .values_mut()
+            .flat_map(|todos| todos.values_mut())
+        {
+            if let Some(index) = todos.iter().position(|todo| todo.id == id) {
+                return Some(todos.remove(index));
+            }
+        }
+        None
+    }
+
+    /// entfernt ein offenes todo, ohne es abzuhaken, und legt es als gelöscht ins Archiv
+    pub fn discard(&mut self, id: u64) -> Option<Todo> {
+        let mut todo = self.remove(id)?;
+        todo.discard();
+        self.history.push(todo.clone());
+        Some(todo)
+    }
+
+    /// hakt das todo an der Stelle index ab und verschiebt es ins Archiv
+    pub fn check(&mut self, channel: &str, user: &str, index: usize) -> Option<Todo> {
+        let todos = self.user_todos_mut(channel, user)?;
+        if index >= todos.len() {
+            return None;
+        }
+        let mut todo = todos.remove(index);
+        todo.complete();
+        self.history.push(todo.clone());
+        Some(todo)
+    }
+
+    /// holt ein abgehaktes oder gelöschtes todo an seine alte Stelle in der Liste des Nutzers zurück
+    pub fn restore(&mut self, mut todo: Todo, index: usize) -> Todo {
+        self.history.retain(|t| t.id != todo.id);
+        todo.reopen();
+        self.next_id = self.next_id.max(todo.id + 1);
+        let todos = self
+            .channels
+            .entry(todo.channel.clone())
+            .or_default()
+            .entry(todo.user.clone())
+            .or_default();
+        todos.insert(index.min(todos.len()), todo.clone());
+        todo
+    }
+
+    /// Anzahl der offenen todos, die ein flush mit diesem Filter löschen würde
+    pub fn count(&self, scope: &FlushScope) -> usize {
+        self.channels
+            .values()
+            .flat_map(|todos| todos.values())
+            .flatten()
+            .filter(|todo| scope.matches(todo))
+            .count()
+    }
+
+    /// erledigte todos, neueste zuerst
+    pub fn history(&self, query: &HistoryQuery) -> Vec<Todo> {
+        self.history
+            .iter()
+            .rev()
+            .filter(|todo| query.matches(todo))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// ältere todos wissen nicht, zu welchem Channel und Nutzer sie gehören, das wird aus den Schlüsseln ergänzt
+    pub fn fill_missing_metadata(&mut self) {
+        for (channel, todos) in self.channels.iter_mut() {
+            for (user, todos) in todos.iter_mut() {
+                for todo in todos.iter_mut() {
+                    if todo.channel.is_empty() {
+                        todo.channel = channel.clone();
+                    }
+                    if todo.user.is_empty() {
+                        todo.user = user.clone();
+                    }
+                    if todo.display_name.is_empty() {
+                        todo.display_name = user.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    /// entfernt leere Listen, damit sie nicht mitgespeichert werden
+    pub fn without_empty(&self) -> TodoStore {
+        TodoStore {
+            next_id: self.next_id,
+            channels: self
+                .channels
+                .iter()
+                .map(|(channel, todos)| {
+                    (
+                        channel.clone(),
+                        todos
+                            .iter()
+                            .filter(|(_, v)| !v.is_empty())
+                            .map(|(user, v)| (user.clone(), v.clone()))
+                            .collect::<ChannelTodos>(),
+                    )
+                })
+                .filter(|(_, todos)| !todos.is_empty())
+                .collect(),
+            history: self.history.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with(texts: &[(&str, TodoPriority)]) -> TodoStore {
+        let author = TwitchUserBasics {
+            id: String::new(),
+            login: "alice".to_owned(),
+            name: "alice".to_owned(),
+        };
+        let mut store = TodoStore::default();
+        for (index, (text, priority)) in texts.iter().enumerate() {
+            store.add("vanimio", &author, text.to_string(), None);
+            store.set_priority("vanimio", "alice", index, *priority);
+        }
+        store
+    }
+
+    fn texts(store: &TodoStore) -> Vec<String> {
+        store
+            .user_todos("vanimio", "alice")
+            .unwrap()
+            .iter()
+            .map(|todo| todo.text.clone())
+            .collect()
+    }
+
+    fn new_todo(store: &mut TodoStore, text: &str, priority: TodoPriority) -> Todo {
+        Todo {
+            id: store.next_id(),
+            text: text.to_owned(),
+            channel: "vanimio".to_owned(),
+            user: "alice".to_owned(),
+            priority,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn insert_sorts_by_priority() {
+        let mut store = store_with(&[("a", TodoPriority::High), ("b", TodoPriority::Low)]);
+        let todo = new_todo(&mut store, "c", TodoPriority::Normal);
+        store.insert(todo);
+        assert_eq!(texts(&store), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn insert_after_move_keeps_moved_todo_in_place() {
+        let mut store = store_with(&[
+            ("a", TodoPriority::High),
+            ("b", TodoPriority::Normal),
+            ("c", TodoPriority::Low),
+        ]);
+        // das unwichtige todo wurde mit !todomove nach oben geschoben
+        store.move_todo("vanimio", "alice", 2, 0);
+        assert_eq!(texts(&store), ["c", "a", "b"]);
+
+        let todo = new_todo(&mut store, "d", TodoPriority::Normal);
+        store.insert(todo);
+        assert_eq!(texts(&store), ["c", "a", "b", "d"]);
+
+        let todo = new_todo(&mut store, "e", TodoPriority::High);
+        store.insert(todo);
+        assert_eq!(texts(&store), ["c", "a", "e", "b", "d"]);
+    }
+}
@@ -0,0 +1,96 @@
+//! Rückgängig machen von !check und den Aktionen der mods (!todoflush)
+//! die Einträge liegen nur im Speicher und verfallen nach undo_window_secs aus der config.json
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::todo::Todo;
+
+/// ein entferntes todo zusammen mit der Stelle, an der es in der Liste des Nutzers stand
+#[derive(Clone, Debug)]
+pub struct RemovedTodo {
+    pub todo: Todo,
+    pub index: usize,
+}
+
+struct UndoEntry {
+    time: Instant,
+    todos: Vec<RemovedTodo>,
+}
+
+pub struct UndoLog {
+    window: Duration,
+    /// abgehakte todos nach Channel und Nutzer, neueste zuletzt
+    checks: HashMap<(String, String), Vec<UndoEntry>>,
+    /// von mods entfernte todos nach dem Channel, in dem das Kommando geschickt wurde
+    mod_actions: HashMap<String, Vec<UndoEntry>>,
+}
+
+impl UndoLog {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            checks: HashMap::new(),
+            mod_actions: HashMap::new(),
+        }
+    }
+
+    pub fn record_check(&mut self, todo: Todo, index: usize) {
+        self.prune();
+        self.checks
+            .entry((todo.channel.clone(), todo.user.clone()))
+            .or_default()
+            .push(UndoEntry {
+                time: Instant::now(),
+                todos: vec![RemovedTodo { todo, index }],
+            });
+    }
+
+    pub fn record_mod_action(&mut self, channel: &str, todos: Vec<RemovedTodo>) {
+        self.prune();
+        if todos.is_empty() {
+            return;
+        }
+        self.mod_actions
+            .entry(channel.to_owned())
+            .or_default()
+            .push(UndoEntry {
+                time: Instant::now(),
+                todos,
+            });
+    }
+
+    /// das zuletzt abgehakte todo des Nutzers, falls es noch im Zeitfenster liegt
+    pub fn take_check(&mut self, channel: &str, user: &str) -> Option<RemovedTodo> {
+        self.prune();
+        self.checks
+            .get_mut(&(channel.to_owned(), user.to_owned()))
+            .and_then(Vec::pop)
+            .and_then(|entry| entry.todos.into_iter().next())
+    }
+
+    /// die todos der letzten Aktion eines mods in dem Channel, falls sie noch im Zeitfenster liegt
+    pub fn take_mod_action(&mut self, channel: &str) -> Option<Vec<RemovedTodo>> {
+        self.prune();
+        self.mod_actions
+            .get_mut(channel)
+            .and_then(Vec::pop)
+            .map(|entry| entry.todos)
+    }
+
+    /// entfernt alle Einträge, die älter als das Zeitfenster sind
+    fn prune(&mut self) {
+        let window = self.window;
+        let is_recent = |entry: &UndoEntry| entry.time.elapsed() <= window;
+        self.checks.retain(|_, entries| {
+            entries.retain(is_recent);
+            !entries.is_empty()
+        });
+        self.mod_actions.retain(|_, entries| {
+            entries.retain(is_recent);
+            !entries.is_empty()
+        });
+    }
+}
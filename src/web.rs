@@ -1,12 +1,13 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{collections::HashMap, fmt::Display, sync::Arc, time::Duration};
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{sse::Event, Html, Sse},
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{sse::Event, Html, IntoResponse, Sse},
     routing::{get, post},
     Json, Router,
 };
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
 use futures::{stream, Stream};
 use serde::{Deserialize, Serialize};
 use tokio::{
@@ -21,10 +22,15 @@ use tower_http::cors::CorsLayer;
 use twitch_irc::{login::LoginCredentials, transport::Transport};
 
 use crate::{
-    bot::{hash_message, Data},
+    bot::Data,
     channel_joiner::ChannelJoiner,
-    communication::TodoUpdate,
-    config::ModSet,
+    communication::{send_todo_update, TodoSubscribers, TodoUpdate, SUBSCRIBER_BUFFER},
+    config::{BotConfig, ModSet},
+    import_export::{self, normalize_name, parse_todos, validate_todos, TransferFormat},
+    leaderboard::Leaderboard,
+    stats::UserStats,
+    timer::{Timer, TimerUpdate, Timers},
+    todo::{HistoryQuery, Todo, STREAM_TODOS},
 };
 
 const ROOT_PAGE: &str = if cfg!(feature = "de") {
@@ -35,12 +41,21 @@ const ROOT_PAGE: &str = if cfg!(feature = "de") {
 
 const TODOS_PAGE: &str = include_str!("./todos.html");
 
+const TIMERS_PAGE: &str = include_str!("./timers.html");
+
+const LEADERBOARD_PAGE: &str = include_str!("./leaderboard.html");
+
+/// Plätze pro Bestenliste im Overlay, wenn limit nicht angegeben ist
+const DEFAULT_LEADERBOARD_LIMIT: usize = 10;
+
 pub fn spawn_axum_worker<T: Transport, C: LoginCredentials>(
     joiner: Arc<Mutex<ChannelJoiner<T, C>>>,
     mod_set: Arc<Mutex<ModSet>>,
     stop_sender: Arc<Mutex<Sender<()>>>,
-    todo_updates: Arc<Mutex<Vec<Sender<TodoUpdate>>>>,
+    todo_updates: TodoSubscribers,
+    timers: Timers,
     data: Data,
+    config: BotConfig,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         let app = Router::new()
@@ -57,9 +72,21 @@ pub fn spawn_axum_worker<T: Transport, C: LoginCredentials>(
             .with_state(stop_sender)
             .route("/todos", get(todos_index))
             .route("/get_todos", get(get_todos))
-            .with_state(data)
+            .route("/get_history", get(get_history))
+            .route("/get_stats", get(get_stats))
+            .route("/leaderboard", get(leaderboard_index))
+            .route("/get_leaderboard", get(get_leaderboard))
+            .route("/export_todos", get(export_todos))
+            .with_state(data.clone())
+            .route("/import_todos", post(import_todos))
+            .with_state((data, todo_updates.clone(), config))
             .route("/todos_sse", get(sse_handler))
+            .route("/leaderboard_sse", get(leaderboard_sse_handler))
             .with_state(todo_updates)
+            .route("/timers", get(timers_index))
+            .route("/get_timers", get(get_timers))
+            .route("/timers_sse", get(timers_sse_handler))
+            .with_state(timers)
             .layer(CorsLayer::permissive());
         let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
         axum::serve(listener, app).await.unwrap()
@@ -74,10 +101,37 @@ pub async fn todos_index() -> Html<&'static str> {
     Html(TODOS_PAGE)
 }
 
+pub async fn timers_index() -> Html<&'static str> {
+    Html(TIMERS_PAGE)
+}
+
+pub async fn leaderboard_index() -> Html<&'static str> {
+    Html(LEADERBOARD_PAGE)
+}
+
+/// optionaler Filter für die Overlay-Endpunkte, damit jeder Streamer nur die todos seines Channels sieht
+/// mit tag zeigt das Overlay nur die todos mit diesem Tag (z.B. ein eigenes Overlay für #lernen)
+#[derive(Deserialize)]
+pub struct ChannelQuery {
+    channel: Option<String>,
+    tag: Option<String>,
+}
+
+impl ChannelQuery {
+    fn matches(&self, channel: &String) -> bool {
+        self.channel.as_ref().is_none_or(|c| c == channel)
+    }
+
+    fn matches_tag(&self, todo: &Todo) -> bool {
+        self.tag.as_ref().is_none_or(|tag| todo.has_tag(tag))
+    }
+}
+
 pub async fn sse_handler(
-    State(todo_updates): State<Arc<Mutex<Vec<Sender<TodoUpdate>>>>>,
+    State(todo_updates): State<TodoSubscribers>,
+    Query(query): Query<ChannelQuery>,
 ) -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
-    let (send, mut recv) = mpsc::channel(1);
+    let (send, mut recv) = mpsc::channel(SUBSCRIBER_BUFFER);
 
     todo_updates.lock().await.push(send);
 
@@ -87,17 +141,46 @@ pub async fn sse_handler(
         let mut todo_data = TodoStatusMessage::default();
 
         while let Ok(r) = recv.try_recv() {
+            if !query.matches(r.channel()) {
+                continue;
+            }
             match r {
-                TodoUpdate::AddTodo {
-                    user,
-                    todo_message,
-                    uuid,
-                } => {
-                    todo_data.new_todos.push((uuid, user, todo_message));
+                // die Liste des Streamers hat ihren eigenen Bereich und wird nicht nach Tags gefiltert
+                TodoUpdate::EditTodo(todo) if todo.is_stream_todo() => {
+                    todo_data.checks.retain(|id| *id != todo.id);
+                    todo_data.stream_todos.push(todo);
+                }
+                TodoUpdate::AddTodo(todo) => todo_data.add(&query, todo),
+                TodoUpdate::ImportTodos { channel: _, todos }
+                | TodoUpdate::RestoreTodos { todos, .. } => {
+                    for todo in todos {
+                        todo_data.add(&query, todo);
+                    }
                 }
-                TodoUpdate::CheckTodo(uuid) => {
-                    todo_data.checks.push(uuid);
+                TodoUpdate::CheckTodo { channel: _, ids } => {
+                    todo_data.checks.extend(ids);
                 }
+                // mit Tag Filter kann ein geändertes todo dazukommen oder wegfallen
+                TodoUpdate::EditTodo(todo) if query.tag.is_some() => match query.matches_tag(&todo)
+                {
+                    true => todo_data.new_todos.push(todo),
+                    false => todo_data.checks.push(todo.id),
+                },
+                TodoUpdate::EditTodo(todo) => {
+                    todo_data.edits.push(todo);
+                }
+                TodoUpdate::StartTodo(todo) if !query.matches_tag(&todo) => {}
+                TodoUpdate::StartTodo(todo) => {
+                    todo_data.starts.push(todo);
+                }
+                TodoUpdate::ReorderTodos { channel: _, ids } => {
+                    todo_data.reorders.push(ids);
+                }
+                TodoUpdate::FlushTodos { channel: _, ids } => {
+                    todo_data.flushes.extend(ids);
+                }
+                // betrifft nur die Bestenliste
+                TodoUpdate::StreamEnded { .. } => {}
             }
         }
         let event_message = match todo_data.is_empty() {
@@ -116,24 +199,325 @@ pub async fn sse_handler(
     )
 }
 
-pub async fn get_todos(State(data): State<Data>) -> Json<Vec<(String, Vec<(String, u64)>)>> {
-    let data = data.lock().await.clone();
+pub async fn get_timers(
+    State(timers): State<Timers>,
+    Query(query): Query<ChannelQuery>,
+) -> Json<Vec<Timer>> {
+    let store = timers.store.lock().await;
+    Json(
+        store
+            .timers
+            .iter()
+            .filter(|timer| query.matches(&timer.channel))
+            .cloned()
+            .collect(),
+    )
+}
+
+pub async fn timers_sse_handler(
+    State(timers): State<Timers>,
+    Query(query): Query<ChannelQuery>,
+) -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let (send, mut recv) = mpsc::channel(SUBSCRIBER_BUFFER);
+
+    timers.subscribers.lock().await.push(send);
+
+    drop(timers);
+
+    let stream = stream::repeat_with(move || {
+        let mut timer_data = TimerStatusMessage::default();
+
+        while let Ok(r) = recv.try_recv() {
+            if !query.matches(r.channel()) {
+                continue;
+            }
+            match r {
+                TimerUpdate::Started(timer) => timer_data.started.push(timer),
+                TimerUpdate::Ended(timer) => timer_data.ended.push(timer),
+            }
+        }
+        let event_message = match timer_data.is_empty() {
+            true => TimerSSEUpdate::KeepAlive,
+            false => TimerSSEUpdate::TimerUpdate(timer_data),
+        };
+        Event::default().data(serde_json::to_string(&event_message).unwrap())
+    })
+    .map(Ok)
+    .throttle(Duration::from_secs(1));
+
+    Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(20))
+            .text(SSEUpdate::KeepAlive.to_string()),
+    )
+}
+
+/// Antwort von /get_todos, die Liste des Streamers steht getrennt von denen der Zuschauer
+#[derive(Serialize)]
+pub struct TodoLists {
+    todos: Vec<Todo>,
+    stream_todos: Vec<Todo>,
+}
+
+pub async fn get_todos(
+    State(data): State<Data>,
+    Query(query): Query<ChannelQuery>,
+) -> Json<TodoLists> {
+    let data = data.lock().await;
+    let (stream_todos, mut slots): (Vec<&Todo>, Vec<&Todo>) = data
+        .channels
+        .iter()
+        .filter(|(channel, _)| query.matches(channel))
+        .flat_map(|(_, todos)| todos.values())
+        .flatten()
+        .partition(|todo| todo.is_stream_todo());
+    slots.retain(|todo| query.matches_tag(todo));
+    slots.sort_by_key(|todo| todo.created_at);
+
+    // die Plätze bleiben nach Erstellung sortiert, jeder Nutzer füllt seine Plätze aber in der Reihenfolge seiner Liste
+    let mut user_lists: HashMap<(&String, &String), std::slice::Iter<Todo>> = data
+        .channels
+        .iter()
+        .flat_map(|(channel, todos)| {
+            todos
+                .iter()
+                .map(move |(user, t)| ((channel, user), t.iter()))
+        })
+        .collect();
+    let todos = slots
+        .into_iter()
+        .filter_map(|slot| {
+            user_lists
+                .get_mut(&(&slot.channel, &slot.user))
+                .and_then(|todos| todos.find(|todo| query.matches_tag(todo)))
+                .cloned()
+        })
+        .collect();
+    Json(TodoLists {
+        todos,
+        stream_todos: stream_todos.into_iter().cloned().collect(),
+    })
+}
+
+/// Filter für /get_history, from und to sind entweder ein Datum (2024-12-24, lokale Zeit) oder RFC 3339
+#[derive(Deserialize)]
+pub struct HistoryParams {
+    channel: Option<String>,
+    user: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    limit: Option<usize>,
+}
+
+/// ein reines Datum wird als Tagesanfang interpretiert, bei `to` als Anfang des Folgetages,
+/// damit der angegebene Tag mit eingeschlossen ist
+fn parse_date_filter(value: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(date_time.to_utc());
+    }
+    let mut date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    if end_of_day {
+        date = date.succ_opt()?;
+    }
+    date.and_time(NaiveTime::MIN)
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|date_time| date_time.to_utc())
+}
+
+pub async fn get_history(
+    State(data): State<Data>,
+    Query(params): Query<HistoryParams>,
+) -> Result<Json<Vec<Todo>>, StatusCode> {
+    let parse = |value: Option<String>, end_of_day: bool| match value {
+        None => Ok(None),
+        Some(value) => parse_date_filter(&value, end_of_day)
+            .map(Some)
+            .ok_or(StatusCode::BAD_REQUEST),
+    };
+    let query = HistoryQuery {
+        channel: params.channel,
+        user: params.user,
+        from: parse(params.from, false)?,
+        to: parse(params.to, true)?,
+        limit: params.limit,
+        include_removed: false,
+    };
+    Ok(Json(data.lock().await.history(&query)))
+}
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    channel: Option<String>,
+    limit: Option<usize>,
+}
+
+pub async fn get_leaderboard(
+    State(data): State<Data>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Json<Leaderboard> {
+    Json(data.lock().await.leaderboard(
+        query.channel.as_deref(),
+        query.limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT),
+    ))
+}
+
+/// meldet dem Overlay, dass sich die Bestenliste geändert haben kann, es holt sie dann neu über /get_leaderboard
+pub async fn leaderboard_sse_handler(
+    State(todo_updates): State<TodoSubscribers>,
+    Query(query): Query<ChannelQuery>,
+) -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let (send, mut recv) = mpsc::channel(SUBSCRIBER_BUFFER);
+
+    todo_updates.lock().await.push(send);
+
+    drop(todo_updates);
+
+    let stream = stream::repeat_with(move || {
+        let mut changed = false;
+
+        while let Ok(r) = recv.try_recv() {
+            if !query.matches(r.channel()) {
+                continue;
+            }
+            // abgehakte todos zählen, mit !todoundo zurückgeholte wieder nicht und mit !streamend beginnt
+            // die Bestenliste für den Stream neu, neue oder von mods gelöschte todos ändern nichts
+            if let TodoUpdate::CheckTodo { .. }
+            | TodoUpdate::RestoreTodos {
+                unchecked: true, ..
+            }
+            | TodoUpdate::StreamEnded { .. } = r
+            {
+                changed = true;
+            }
+        }
+        let event_message = match changed {
+            true => LeaderboardSSEUpdate::Changed,
+            false => LeaderboardSSEUpdate::KeepAlive,
+        };
+        Event::default().data(serde_json::to_string(&event_message).unwrap())
+    })
+    .map(Ok)
+    .throttle(Duration::from_secs(1));
+
+    Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(20))
+            .text(SSEUpdate::KeepAlive.to_string()),
+    )
+}
+
+/// Filter für /get_stats
+#[derive(Deserialize)]
+pub struct StatsParams {
+    channel: Option<String>,
+    user: Option<String>,
+}
+
+pub async fn get_stats(
+    State(data): State<Data>,
+    Query(params): Query<StatsParams>,
+) -> Json<Vec<UserStats>> {
     Json(
-        data.iter()
-            .map(|(name, todos)| {
-                (
-                    name.clone(),
-                    todos
-                        .clone()
-                        .into_iter()
-                        .map(|v| (v.clone(), hash_message(&name, &v)))
-                        .collect(),
-                )
-            })
-            .collect::<Vec<(String, Vec<(String, u64)>)>>(),
+        data.lock()
+            .await
+            .stats(params.channel.as_deref(), params.user.as_deref()),
     )
 }
 
+/// Parameter für Import und Export, beim Import sind channel und user die Standardwerte für Einträge ohne
+#[derive(Deserialize)]
+pub struct TransferParams {
+    format: TransferFormat,
+    channel: Option<String>,
+    user: Option<String>,
+}
+
+pub async fn export_todos(
+    State(data): State<Data>,
+    Query(params): Query<TransferParams>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let channel = params.channel.as_deref().map(normalize_name);
+    let user = params.user.as_deref().map(normalize_name);
+    let data = data.lock().await;
+    let mut todos: Vec<Todo> = data
+        .channels
+        .iter()
+        .filter(|(c, _)| channel.as_ref().is_none_or(|channel| channel == *c))
+        .flat_map(|(_, todos)| todos.iter())
+        // die Liste des Streamers wird nicht exportiert, sie kann auch nicht importiert werden
+        .filter(|(user, _)| user.as_str() != STREAM_TODOS)
+        .filter(|(u, _)| user.as_ref().is_none_or(|user| user == *u))
+        .flat_map(|(_, todos)| todos.iter().cloned())
+        .collect();
+    drop(data);
+    // stabil sortieren, damit die Reihenfolge innerhalb einer Liste erhalten bleibt
+    todos.sort_by(|a, b| (&a.channel, &a.user).cmp(&(&b.channel, &b.user)));
+
+    let content = import_export::export_todos(&todos, params.format).map_err(|e| {
+        log::error!("error while exporting todos: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                params.format.content_type().to_owned(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"todos.{}\"",
+                    params.format.file_extension()
+                ),
+            ),
+        ],
+        content,
+    ))
+}
+
+#[derive(Serialize)]
+pub struct ImportResult {
+    imported: usize,
+    duplicates: usize,
+    /// übersprungen, weil sie die limits aus der config.json überschreiten würden
+    over_limit: usize,
+}
+
+/// importierte todos werden zu den bestehenden hinzugefügt, doppelte (gleicher Nutzer und Text)
+/// und solche, die die limits überschreiten, werden übersprungen
+pub async fn import_todos(
+    State((data, todo_updates, config)): State<(Data, TodoSubscribers, BotConfig)>,
+    Query(params): Query<TransferParams>,
+    payload: String,
+) -> Result<Json<ImportResult>, (StatusCode, String)> {
+    let entries = parse_todos(&payload, params.format)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid file: {e}")))?;
+    let todos = validate_todos(entries, params.channel.as_ref(), params.user.as_ref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let total = todos.len();
+    let (imported, over_limit) = data.lock().await.import(todos, &config);
+    log::warn!("imported {} of {total} todos", imported.len());
+
+    let result = ImportResult {
+        imported: imported.len(),
+        duplicates: total - imported.len() - over_limit,
+        over_limit,
+    };
+    let mut by_channel: HashMap<String, Vec<Todo>> = HashMap::new();
+    for todo in imported {
+        by_channel
+            .entry(todo.channel.clone())
+            .or_default()
+            .push(todo);
+    }
+    for (channel, todos) in by_channel {
+        send_todo_update(&todo_updates, TodoUpdate::ImportTodos { channel, todos }).await;
+    }
+    Ok(Json(result))
+}
+
 pub async fn post_channels<T: Transport, C: LoginCredentials>(
     State(joiner): State<Arc<Mutex<ChannelJoiner<T, C>>>>,
     Json(payload): Json<Vec<String>>,
@@ -189,24 +573,45 @@ pub async fn send_stop(
     StatusCode::BAD_REQUEST
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct TodoStatusMessage {
-    new_todos: Vec<(u64, String, String)>,
+    new_todos: Vec<Todo>,
     checks: Vec<u64>,
+    /// geänderte todos, die im Overlay ersetzt werden
+    edits: Vec<Todo>,
+    /// neue und geänderte todos der Liste des Streamers (!streamtodo), abgehakte stehen bei checks
+    stream_todos: Vec<Todo>,
+    /// todos, die jetzt in Arbeit sind (!todostart)
+    starts: Vec<Todo>,
+    /// neue Reihenfolgen der Listen einzelner Nutzer
+    reorders: Vec<Vec<u64>>,
+    /// von mods gelöschte todos
+    flushes: Vec<u64>,
 }
 
-impl Default for TodoStatusMessage {
-    fn default() -> Self {
-        Self {
-            new_todos: vec![],
-            checks: vec![],
+impl TodoStatusMessage {
+    fn add(&mut self, query: &ChannelQuery, todo: Todo) {
+        // die Liste des Streamers hat ihren eigenen Bereich und wird nicht nach Tags gefiltert
+        if todo.is_stream_todo() {
+            self.checks.retain(|id| *id != todo.id);
+            self.flushes.retain(|id| *id != todo.id);
+            self.stream_todos.push(todo);
+        } else if query.matches_tag(&todo) {
+            // ein zurückgeholtes todo, das im selben Event abgehakt wurde, soll sichtbar bleiben
+            self.checks.retain(|id| *id != todo.id);
+            self.flushes.retain(|id| *id != todo.id);
+            self.new_todos.push(todo);
         }
     }
-}
 
-impl TodoStatusMessage {
     pub fn is_empty(&self) -> bool {
-        self.new_todos.is_empty() && self.checks.is_empty()
+        self.new_todos.is_empty()
+            && self.checks.is_empty()
+            && self.edits.is_empty()
+            && self.starts.is_empty()
+            && self.stream_todos.is_empty()
+            && self.reorders.is_empty()
+            && self.flushes.is_empty()
     }
 }
 
@@ -217,8 +622,32 @@ pub enum SSEUpdate {
     InitMessage(HashMap<String, String>),
 }
 
-impl ToString for SSEUpdate {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+impl Display for SSEUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
+
+#[derive(Serialize, Default)]
+pub struct TimerStatusMessage {
+    started: Vec<Timer>,
+    ended: Vec<Timer>,
+}
+
+impl TimerStatusMessage {
+    pub fn is_empty(&self) -> bool {
+        self.started.is_empty() && self.ended.is_empty()
+    }
+}
+
+#[derive(Serialize)]
+pub enum TimerSSEUpdate {
+    TimerUpdate(TimerStatusMessage),
+    KeepAlive,
+}
+
+#[derive(Serialize)]
+pub enum LeaderboardSSEUpdate {
+    Changed,
+    KeepAlive,
+}